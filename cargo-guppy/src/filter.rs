@@ -0,0 +1,326 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Options for filtering the packages returned by queries.
+
+use cargo_metadata::PackageId;
+use guppy::graph::{DependencyLink, PackageMetadata, PackageSource};
+use std::fmt;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct FilterOptions {
+    /// Only include packages from this source ("registry" includes crates.io)
+    #[structopt(
+        long,
+        possible_values = &["crates-io", "registry", "git", "path"]
+    )]
+    pub source: Option<SourceKind>,
+
+    /// Only include packages that use this Rust edition, e.g. 2018 or 2021
+    #[structopt(long)]
+    pub edition: Option<String>,
+
+    /// Exclude packages whose names match this glob from the output (can be repeated)
+    #[structopt(long, number_of_values = 1)]
+    pub exclude_name: Vec<String>,
+
+    /// Don't traverse into the dependencies of workspace members other than the roots
+    #[structopt(long)]
+    pub stop_at_workspace: bool,
+
+    /// Don't follow links that are only build dependencies
+    #[structopt(long)]
+    pub no_build: bool,
+
+    /// Don't follow links that are only dev dependencies
+    #[structopt(long)]
+    pub no_dev: bool,
+}
+
+impl FilterOptions {
+    /// Returns true if this package should be included in the output.
+    pub fn accepts(&self, package: &PackageMetadata) -> bool {
+        let source_matches = match self.source {
+            Some(source_kind) => source_kind.matches(package.source()),
+            None => true,
+        };
+        let edition_matches = match &self.edition {
+            Some(edition) => package.edition() == edition,
+            None => true,
+        };
+        let name_excluded = self
+            .exclude_name
+            .iter()
+            .any(|pattern| glob_matches(pattern, package.name()));
+        source_matches && edition_matches && !name_excluded
+    }
+
+    /// Returns a filter for the dependency links that should be followed from the given roots.
+    pub fn make_link_filter<'a>(&self, root_ids: &'a [PackageId]) -> LinkFilter<'a> {
+        let mut filter = LinkFilter::all();
+        if self.stop_at_workspace {
+            // Other workspace members are still included, but their dependencies aren't followed.
+            filter = filter.and(LinkFilter::new(move |link| {
+                !link.from.in_workspace() || root_ids.contains(link.from.id())
+            }));
+        }
+        if self.no_build || self.no_dev {
+            // Follow links that are present in any of the remaining sections, so that with both
+            // options, links that are both build and dev dependencies are skipped too.
+            let mut kinds = LinkFilter::new(|link| link.edge.normal().is_some());
+            if !self.no_build {
+                kinds = kinds.or(LinkFilter::new(|link| link.edge.build().is_some()));
+            }
+            if !self.no_dev {
+                kinds = kinds.or(LinkFilter::new(|link| link.edge.dev().is_some()));
+            }
+            filter = filter.and(kinds);
+        }
+        filter
+    }
+}
+
+/// Returns true if `name` matches `pattern`, where `*` matches any sequence of characters and `?`
+/// matches any single character.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<_> = pattern.chars().collect();
+    let name: Vec<_> = name.chars().collect();
+
+    // Match greedily, backtracking to the most recent `*` on a mismatch.
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    // Let the `*` absorb one more character.
+                    backtrack = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// A predicate over dependency links, which can be combined with other predicates.
+pub struct LinkFilter<'a> {
+    predicate: Box<dyn Fn(&DependencyLink<'_>) -> bool + 'a>,
+}
+
+impl<'a> LinkFilter<'a> {
+    /// Creates a new filter out of the given predicate.
+    pub fn new(predicate: impl Fn(&DependencyLink<'_>) -> bool + 'a) -> Self {
+        Self {
+            predicate: Box::new(predicate),
+        }
+    }
+
+    /// Returns a filter that accepts every link.
+    pub fn all() -> Self {
+        Self::new(|_| true)
+    }
+
+    /// Returns a filter that accepts links accepted by both this filter and `other`.
+    pub fn and(self, other: LinkFilter<'a>) -> Self {
+        Self::new(move |link| self.accepts(link) && other.accepts(link))
+    }
+
+    /// Returns a filter that accepts links accepted by either this filter or `other`.
+    pub fn or(self, other: LinkFilter<'a>) -> Self {
+        Self::new(move |link| self.accepts(link) || other.accepts(link))
+    }
+
+    /// Returns true if this link is accepted by the filter.
+    pub fn accepts(&self, link: &DependencyLink<'_>) -> bool {
+        (self.predicate)(link)
+    }
+}
+
+impl<'a> fmt::Debug for LinkFilter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LinkFilter").finish()
+    }
+}
+
+/// The kind of source a package can come from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SourceKind {
+    /// crates.io.
+    CratesIo,
+    /// Any registry, including crates.io.
+    Registry,
+    /// A git repository.
+    Git,
+    /// A local path.
+    Path,
+}
+
+impl SourceKind {
+    /// Returns true if this package source is of this kind.
+    pub fn matches(self, source: &PackageSource) -> bool {
+        matches!(
+            (self, source),
+            (SourceKind::CratesIo, PackageSource::CratesIo)
+                | (SourceKind::Registry, PackageSource::CratesIo)
+                | (SourceKind::Registry, PackageSource::Registry(_))
+                | (SourceKind::Git, PackageSource::Git { .. })
+                | (SourceKind::Path, PackageSource::Path(_))
+        )
+    }
+}
+
+impl FromStr for SourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "crates-io" => Ok(SourceKind::CratesIo),
+            "registry" => Ok(SourceKind::Registry),
+            "git" => Ok(SourceKind::Git),
+            "path" => Ok(SourceKind::Path),
+            other => Err(format!("unrecognized source kind '{}'", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use guppy::graph::PackageGraph;
+
+    fn kind_filter_opts(no_build: bool, no_dev: bool) -> FilterOptions {
+        FilterOptions {
+            source: None,
+            edition: None,
+            exclude_name: vec![],
+            stop_at_workspace: false,
+            no_build,
+            no_dev,
+        }
+    }
+
+    #[test]
+    fn dependency_kind_filters() {
+        let package_graph =
+            PackageGraph::from_json(include_str!("../../guppy/fixtures/metadata_libra.json"))
+                .expect("valid metadata");
+        let links: Vec<_> = package_graph.select_all().into_iter_links(None).collect();
+        assert!(
+            links.iter().any(|link| link.edge.build_only()),
+            "libra has build-only links"
+        );
+        assert!(
+            links.iter().any(|link| link.edge.dev_only()),
+            "libra has dev-only links"
+        );
+
+        let all = kind_filter_opts(false, false).make_link_filter(&[]);
+        let no_build = kind_filter_opts(true, false).make_link_filter(&[]);
+        let no_dev = kind_filter_opts(false, true).make_link_filter(&[]);
+        let runtime = kind_filter_opts(true, true).make_link_filter(&[]);
+        for link in &links {
+            let edge = link.edge;
+            assert!(all.accepts(link), "no filters: {:?}", edge);
+            assert_eq!(
+                no_build.accepts(link),
+                !edge.build_only(),
+                "--no-build: {:?}",
+                edge
+            );
+            assert_eq!(
+                no_dev.accepts(link),
+                !edge.dev_only(),
+                "--no-dev: {:?}",
+                edge
+            );
+            assert_eq!(
+                runtime.accepts(link),
+                edge.normal().is_some(),
+                "--no-build --no-dev: {:?}",
+                edge
+            );
+        }
+    }
+
+    #[test]
+    fn link_filter_combinators() {
+        let package_graph =
+            PackageGraph::from_json(include_str!("../../guppy/fixtures/metadata1.json"))
+                .expect("valid metadata");
+        let link = package_graph
+            .select_all()
+            .into_iter_links(None)
+            .next()
+            .expect("metadata1 has links");
+
+        let accept = LinkFilter::all;
+        let reject = || LinkFilter::new(|_| false);
+        assert!(accept().and(accept()).accepts(&link));
+        assert!(!accept().and(reject()).accepts(&link));
+        assert!(!reject().and(accept()).accepts(&link));
+        assert!(accept().or(reject()).accepts(&link));
+        assert!(reject().or(accept()).accepts(&link));
+        assert!(!reject().or(reject()).accepts(&link));
+    }
+
+    #[test]
+    fn glob_matching() {
+        for (pattern, name, expected) in &[
+            // Literal patterns.
+            ("serde", "serde", true),
+            ("serde", "serde_json", false),
+            // `*` at the start, middle and end.
+            ("*-sys", "openssl-sys", true),
+            ("*-sys", "openssl", false),
+            ("*-sys", "-sys", true),
+            ("winapi-*-gnu", "winapi-x86_64-pc-windows-gnu", true),
+            ("winapi-*-gnu", "winapi-x86_64-pc-windows-msvc", false),
+            ("winapi-*", "winapi-util", true),
+            ("winapi-*", "winapi-", true),
+            ("winapi-*", "winapi", false),
+            ("*", "anything", true),
+            ("*", "", true),
+            // Backtracking past an earlier partial match.
+            ("*ab", "aab", true),
+            ("a*b*c", "abbbc", true),
+            ("a*b*c", "abcb", false),
+            // `?` matches exactly one character.
+            ("rand_?", "rand_os", false),
+            ("rand_??", "rand_os", true),
+            ("?", "", false),
+            // Empty patterns only match empty names.
+            ("", "", true),
+            ("", "serde", false),
+            // `**` behaves like `*`.
+            ("**", "serde", true),
+            ("lib**sys", "libz-sys", true),
+            ("lib**sys", "libz-sy", false),
+            // Non-ASCII names are matched by character, not by byte.
+            ("caf?", "café", true),
+            ("caf??", "café", false),
+            ("*é", "café", true),
+            ("🦀-*", "🦀-crate", true),
+        ] {
+            assert_eq!(
+                glob_matches(pattern, name),
+                *expected,
+                "pattern '{}' matching '{}'",
+                pattern,
+                name
+            );
+        }
+    }
+}
@@ -1,7 +1,28 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use guppy::{diff, lockfile::Lockfile, Error};
+use cargo_metadata::{DependencyKind, PackageId};
+use guppy::graph::{kind_str, DependencyDirection, PackageGraph, PackageMetadata, PackageSource};
+use guppy::{
+    diff::{self, compat_version, DiffEntry, PackageDiff},
+    lockfile::Lockfile,
+    Error,
+};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ffi::OsStr;
+use std::fmt;
+use std::iter;
+
+/// The name Cargo uses for crates.io in the `publish` field.
+const CRATES_IO: &str = "crates-io";
+
+mod filter;
+mod metadata;
+mod output;
+
+pub use filter::{FilterOptions, LinkFilter, SourceKind};
+pub use metadata::MetadataOptions;
+pub use output::{package_json, Color, OutputStyles};
 
 pub fn cmd_diff(json: bool, old: &str, new: &str) -> Result<(), Error> {
     let old = Lockfile::from_file(old)?;
@@ -12,24 +33,571 @@ pub fn cmd_diff(json: bool, old: &str, new: &str) -> Result<(), Error> {
     if json {
         println!("{}", serde_json::to_string_pretty(&diff).unwrap());
     } else {
-        print!("{}", diff);
+        print_diff(&diff);
     }
 
     Ok(())
 }
 
-pub fn cmd_count() -> Result<(), Error> {
-    let lockfile = Lockfile::from_file("Cargo.lock")?;
+fn print_diff(diff: &PackageDiff) {
+    fn print_entries(header: &str, entries: &[DiffEntry]) {
+        if entries.is_empty() {
+            return;
+        }
 
-    println!("Third-party Packages: {}", lockfile.third_party_packages());
+        println!("{}", header);
+        for entry in entries {
+            print!(
+                "\t{} {}",
+                entry.package_id.name(),
+                entry.package_id.version()
+            );
+            if let Some((first, rest)) = entry.other_versions.split_first() {
+                print!(" ({}", first.version());
+                for pkg_id in rest {
+                    print!(", {}", pkg_id.version());
+                }
+                print!(")");
+            }
+            println!();
+        }
+        println!();
+    }
+
+    print_entries("Added Packages (Duplicate versions in '()'):", &diff.added);
+    print_entries(
+        "Removed Packages (Remaining versions in '()'):",
+        &diff.removed,
+    );
+
+    if !diff.changed.is_empty() {
+        println!("Updated Packages:");
+        for changed in &diff.changed {
+            println!(
+                "\t{}: {} -> {} ({})",
+                changed.old.name(),
+                changed.old.version(),
+                changed.new.version(),
+                changed.kind.as_str(),
+            );
+        }
+        println!();
+    }
+}
+
+/// Prints the number of third-party (non-path) packages, from `Cargo.lock` or, if a metadata JSON
+/// file was specified, from the package graph.
+pub fn cmd_count(metadata_opts: &MetadataOptions) -> Result<(), Error> {
+    let count = match metadata_opts.metadata_json() {
+        Some(_) => metadata_opts
+            .make_graph()?
+            .packages()
+            .filter(|package| !matches!(package.source(), PackageSource::Path(_)))
+            .count(),
+        None => Lockfile::from_file("Cargo.lock")?.third_party_packages(),
+    };
+
+    println!("Third-party Packages: {}", count);
 
     Ok(())
 }
 
-pub fn cmd_dups() -> Result<(), Error> {
+/// Prints crates that appear at more than one version in `Cargo.lock`.
+///
+/// With `paths`, duplicates are found in the package graph instead, and a dependency path from the
+/// workspace to each version is printed as well.
+pub fn cmd_dups(
+    metadata_opts: &MetadataOptions,
+    styles: &OutputStyles,
+    paths: bool,
+) -> Result<(), Error> {
+    if paths {
+        let package_graph = metadata_opts.make_graph()?;
+        let parents = shortest_path_parents(&package_graph);
+        for (name, packages) in duplicate_packages(&package_graph) {
+            let versions: Vec<_> = packages
+                .iter()
+                .map(|package| version_style(styles, package))
+                .collect();
+            println!("{} ({})", styles.duplicate(name), versions.join(", "));
+            for package in packages {
+                println!(
+                    "\t{}: {}",
+                    version_style(styles, package),
+                    format_path(&package_graph, &parents, package.id())
+                );
+            }
+        }
+        return Ok(());
+    }
+
     let lockfile = Lockfile::from_file("Cargo.lock")?;
+    for (name, duplicates) in lockfile.duplicates() {
+        let versions: Vec<_> = duplicates
+            .iter()
+            .map(|pkg_id| match pkg_id.source() {
+                Some(_) => styles.third_party(pkg_id.version()),
+                None => styles.workspace(pkg_id.version()),
+            })
+            .collect();
+        println!("{} ({})", styles.duplicate(name), versions.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Formats the version of a package, styled by whether it's a path or third-party package.
+fn version_style(styles: &OutputStyles, package: &PackageMetadata) -> String {
+    package_style(styles, package, package.version())
+}
+
+/// Formats the ID of a package, styled by whether it's a path or third-party package.
+fn id_style(styles: &OutputStyles, package: &PackageMetadata) -> String {
+    package_style(styles, package, package.id())
+}
+
+fn package_style(
+    styles: &OutputStyles,
+    package: &PackageMetadata,
+    text: impl fmt::Display,
+) -> String {
+    match package.source() {
+        PackageSource::Path(_) => styles.workspace(text),
+        _ => styles.third_party(text),
+    }
+}
+
+/// Returns the packages in the graph that share a name with another package, grouped by name and
+/// sorted by version.
+fn duplicate_packages(package_graph: &PackageGraph) -> BTreeMap<&str, Vec<&PackageMetadata>> {
+    let mut packages_by_name: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for package in package_graph.packages() {
+        packages_by_name
+            .entry(package.name())
+            .or_default()
+            .push(package);
+    }
+    packages_by_name.retain(|_, packages| packages.len() > 1);
+    for packages in packages_by_name.values_mut() {
+        packages.sort();
+    }
+    packages_by_name
+}
+
+/// Prints crates that appear at more than one version, along with a dependency path from the
+/// workspace to each version.
+///
+/// With `deny_major`, only crates at semver-incompatible versions are printed, and an error is
+/// returned if there are any.
+pub fn cmd_check_dups(
+    metadata_opts: &MetadataOptions,
+    styles: &OutputStyles,
+    deny_major: bool,
+) -> Result<(), Error> {
+    let package_graph = metadata_opts.make_graph()?;
+
+    let parents = shortest_path_parents(&package_graph);
+
+    let mut offenders = 0;
+    for (name, packages) in duplicate_packages(&package_graph) {
+        if deny_major {
+            let compat: HashSet<_> = packages
+                .iter()
+                .map(|package| compat_version(package.version()))
+                .collect();
+            if compat.len() < 2 {
+                continue;
+            }
+        }
+        offenders += 1;
+
+        println!("{}:", styles.duplicate(name));
+        for package in packages {
+            println!(
+                "\t{}: {}",
+                version_style(styles, package),
+                format_path(&package_graph, &parents, package.id())
+            );
+        }
+    }
+
+    if deny_major && offenders > 0 {
+        return Err(Error::DepGraphError(format!(
+            "{} crate(s) appear at multiple semver-incompatible versions",
+            offenders
+        )));
+    }
+
+    Ok(())
+}
+
+/// Records the link each package was first reached through in a breadth-first search from the
+/// workspace, so that the paths formatted by `format_path` are the shortest ones.
+fn shortest_path_parents(package_graph: &PackageGraph) -> HashMap<&PackageId, &PackageId> {
+    let mut parents = HashMap::new();
+    let mut queue: VecDeque<_> = package_graph.workspace().member_ids().collect();
+    let mut visited: HashSet<_> = queue.iter().copied().collect();
+    while let Some(package_id) = queue.pop_front() {
+        for link in package_graph
+            .dep_links(package_id)
+            .expect("valid package ID")
+        {
+            if visited.insert(link.to.id()) {
+                parents.insert(link.to.id(), link.from.id());
+                queue.push_back(link.to.id());
+            }
+        }
+    }
+    parents
+}
+
+/// Formats the path from the workspace to a package as `name version -> name version -> ...`.
+fn format_path(
+    package_graph: &PackageGraph,
+    parents: &HashMap<&PackageId, &PackageId>,
+    package_id: &PackageId,
+) -> String {
+    let mut path = vec![package_id];
+    while let Some(parent) = parents.get(path[path.len() - 1]) {
+        path.push(parent);
+    }
+    let path: Vec<_> = path
+        .iter()
+        .rev()
+        .map(|package_id| {
+            let package = package_graph
+                .metadata(package_id)
+                .expect("valid package ID");
+            format!("{} {}", package.name(), package.version())
+        })
+        .collect();
+    path.join(" -> ")
+}
+
+/// Prints the packages in the graph that have build scripts.
+///
+/// With `host_only`, only packages that are built for the host platform alone are printed.
+pub fn cmd_build_scripts(
+    metadata_opts: &MetadataOptions,
+    styles: &OutputStyles,
+    host_only: bool,
+) -> Result<(), Error> {
+    let package_graph = metadata_opts.make_graph()?;
+
+    let mut packages: Vec<_> = if host_only {
+        package_graph
+            .host_target_split(package_graph.workspace().member_ids())?
+            .host
+            .into_iter()
+            .map(|package_id| {
+                package_graph
+                    .metadata(package_id)
+                    .expect("valid package ID")
+            })
+            .collect()
+    } else {
+        package_graph.packages().collect()
+    };
+    packages.retain(|package| package.has_build_script());
+    packages.sort();
+
+    for package in packages {
+        println!("{}", id_style(styles, package));
+    }
+
+    Ok(())
+}
+
+/// Checks that workspace members that can be published to crates.io don't depend on path or git
+/// packages that can't be published.
+///
+/// Dev-dependencies are ignored, since they're stripped out by `cargo publish`. An error is
+/// returned if any violations are found.
+pub fn cmd_check_publish(
+    metadata_opts: &MetadataOptions,
+    styles: &OutputStyles,
+) -> Result<(), Error> {
+    let mut package_graph = metadata_opts.make_graph()?;
+    package_graph.retain_edges(|_, link| !link.edge.dev_only());
+
+    let mut members: Vec<_> = package_graph
+        .workspace()
+        .member_ids()
+        .map(|member_id| package_graph.metadata(member_id).expect("valid package ID"))
+        .filter(|member| member.is_publishable_to(CRATES_IO))
+        .collect();
+    members.sort();
+
+    let mut violations = 0;
+    for member in members {
+        let mut deps: Vec<_> = package_graph
+            .select_transitive_deps(iter::once(member.id()))?
+            .into_iter_ids(None)
+            .map(|package_id| {
+                package_graph
+                    .metadata(package_id)
+                    .expect("valid package ID")
+            })
+            .filter(|package| {
+                let local_source = matches!(
+                    package.source(),
+                    PackageSource::Path(_) | PackageSource::Git { .. }
+                );
+                local_source && !package.is_publishable_to(CRATES_IO)
+            })
+            .collect();
+        deps.sort();
+
+        for dep in deps {
+            println!(
+                "{} is publishable to crates.io, but depends on {}",
+                id_style(styles, member),
+                id_style(styles, dep)
+            );
+            violations += 1;
+        }
+    }
+
+    if violations > 0 {
+        return Err(Error::DepGraphError(format!(
+            "{} dependency(s) on unpublishable packages found",
+            violations
+        )));
+    }
+
+    Ok(())
+}
+
+/// Prints every package with its number of direct dependencies and direct dependents, with the
+/// packages that have the most direct dependencies first.
+pub fn cmd_fanout(metadata_opts: &MetadataOptions, styles: &OutputStyles) -> Result<(), Error> {
+    let package_graph = metadata_opts.make_graph()?;
+
+    let mut counts: Vec<_> = package_graph
+        .packages()
+        .map(|package| {
+            let dep_count = package_graph
+                .direct_dep_count(package.id())
+                .expect("valid package ID");
+            let dependent_count = package_graph
+                .direct_dependent_count(package.id())
+                .expect("valid package ID");
+            (package, dep_count, dependent_count)
+        })
+        .collect();
+    counts.sort_by(|(a, a_deps, _), (b, b_deps, _)| b_deps.cmp(a_deps).then_with(|| a.cmp(b)));
+
+    println!("deps\tdependents\tpackage");
+    for (package, dep_count, dependent_count) in counts {
+        println!(
+            "{}\t{}\t{}",
+            dep_count,
+            dependent_count,
+            id_style(styles, package)
+        );
+    }
+
+    Ok(())
+}
+
+pub fn cmd_select(
+    metadata_opts: &MetadataOptions,
+    styles: &OutputStyles,
+    filter_opts: &FilterOptions,
+    roots: &[String],
+    explain: bool,
+    output_ndjson: bool,
+) -> Result<(), Error> {
+    let mut package_graph = metadata_opts.make_graph()?;
+
+    let root_ids: Vec<PackageId> = if roots.is_empty() {
+        package_graph.workspace().member_ids().cloned().collect()
+    } else {
+        roots
+            .iter()
+            .map(|spec| {
+                let package_id = package_graph.resolve_spec(spec)?;
+                if package_graph.in_workspace(package_id) {
+                    Ok(package_id.clone())
+                } else {
+                    Err(Error::DepGraphError(format!(
+                        "not a workspace member: {}",
+                        package_id
+                    )))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let link_filter = filter_opts.make_link_filter(&root_ids);
+    package_graph.retain_edges(|_, link| link_filter.accepts(&link));
+
+    let selected_ids: Vec<_> = package_graph
+        .select_transitive_deps(&root_ids)?
+        .into_iter_ids(None)
+        .collect();
+    let selected: HashSet<_> = selected_ids.iter().copied().collect();
+
+    for package_id in selected_ids {
+        let package = package_graph
+            .metadata(package_id)
+            .expect("valid package ID");
+        if !filter_opts.accepts(package) {
+            continue;
+        }
+        if output_ndjson {
+            println!("{}", package_json(package));
+            continue;
+        }
+        if !explain {
+            println!("{}", id_style(styles, package));
+            continue;
+        }
 
-    lockfile.duplicate_packages();
+        // Show one representative link that caused this package to be included.
+        let parent_link = package_graph
+            .reverse_dep_links(package_id)
+            .expect("valid package ID")
+            .filter(|link| selected.contains(link.from.id()))
+            .min_by_key(|link| link.from);
+        match parent_link {
+            Some(link) if !root_ids.contains(package_id) => println!(
+                "{} <- {} (as '{}')",
+                id_style(styles, package),
+                id_style(styles, link.from),
+                link.edge.dep_name()
+            ),
+            _ => println!("{} (root)", id_style(styles, package)),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn cmd_summary(metadata_opts: &MetadataOptions) -> Result<(), Error> {
+    let package_graph = metadata_opts.make_graph()?;
+
+    let mut versions_by_name = HashMap::new();
+    for package in package_graph.packages() {
+        versions_by_name
+            .entry(package.name())
+            .or_insert_with(HashSet::new)
+            .insert(package.version());
+    }
+    let duplicate_count = versions_by_name
+        .values()
+        .filter(|versions| versions.len() > 1)
+        .count();
+
+    // Dev-only packages are the ones that are unreachable from the workspace once dev-only links
+    // are removed.
+    let mut no_dev_graph = package_graph.clone();
+    no_dev_graph.retain_edges(|_, link| !link.edge.dev_only());
+    let non_dev_count = no_dev_graph
+        .select_transitive_deps(no_dev_graph.workspace().member_ids())?
+        .into_iter_ids(None)
+        .len();
+
+    let longest_chain = package_graph
+        .depths(DependencyDirection::Forward)
+        .values()
+        .max()
+        .copied()
+        .unwrap_or(0);
+
+    println!("Packages: {}", package_graph.package_count());
+    println!("Links: {}", package_graph.link_count());
+    println!("Duplicate crates: {}", duplicate_count);
+    println!(
+        "Dev-only packages: {}",
+        package_graph.package_count() - non_dev_count
+    );
+    println!("Longest dependency chain: {}", longest_chain);
+
+    Ok(())
+}
+
+/// Prints the declared dependencies of workspace members that don't appear as resolved links.
+///
+/// This is a heuristic: it catches dependencies that were declared but resolved away, e.g. because
+/// they are gated to another platform or are optional and not enabled. It can't tell whether a
+/// resolved dependency is actually used in code.
+pub fn cmd_unused_deps(metadata_opts: &MetadataOptions) -> Result<(), Error> {
+    let package_graph = metadata_opts.make_graph()?;
+
+    for member_id in package_graph.workspace().member_ids() {
+        let member = package_graph.metadata(member_id).expect("valid package ID");
+        let links: Vec<_> = package_graph
+            .dep_links(member_id)
+            .expect("valid package ID")
+            .collect();
+
+        for dep in member.declared_dependencies() {
+            let resolved = links.iter().any(|link| {
+                link.to.name() == dep.name
+                    && match dep.kind {
+                        DependencyKind::Normal => link.edge.normal().is_some(),
+                        DependencyKind::Build => link.edge.build().is_some(),
+                        DependencyKind::Development => link.edge.dev().is_some(),
+                        _ => true,
+                    }
+            });
+            if resolved {
+                continue;
+            }
+
+            let mut notes = vec![kind_str(dep.kind).to_string()];
+            if dep.optional {
+                notes.push("optional".to_string());
+            }
+            if let Some(target) = &dep.target {
+                notes.push(format!("target: {}", target));
+            }
+            println!("{}: {} ({})", member.name(), dep.name, notes.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints workspace members as a tree mirroring the directories they're in.
+pub fn cmd_workspace_tree(
+    metadata_opts: &MetadataOptions,
+    styles: &OutputStyles,
+) -> Result<(), Error> {
+    let package_graph = metadata_opts.make_graph()?;
+    let workspace = package_graph.workspace();
+
+    println!("{}", workspace.root().display());
+    // The directories printed for the previous member. Members are sorted by path, so a directory
+    // only needs to be printed once, before the first member inside it.
+    let mut prev_dirs: Vec<&OsStr> = vec![];
+    for (path, member_id) in workspace.members() {
+        let package = package_graph.metadata(member_id).expect("valid package ID");
+        let dirs: Vec<_> = path.iter().collect();
+        let common = dirs
+            .iter()
+            .zip(&prev_dirs)
+            .take_while(|(dir, prev_dir)| dir == prev_dir)
+            .count();
+
+        match dirs.split_last() {
+            Some((member_dir, parents)) => {
+                for (depth, dir) in parents.iter().enumerate().skip(common) {
+                    println!("{}{}/", "  ".repeat(depth + 1), dir.to_string_lossy());
+                }
+                println!(
+                    "{}{}/ ({})",
+                    "  ".repeat(dirs.len()),
+                    member_dir.to_string_lossy(),
+                    styles.workspace(package.name())
+                );
+            }
+            // The workspace root is itself a member.
+            None => println!("  ./ ({})", styles.workspace(package.name())),
+        }
+        prev_dirs = dirs;
+    }
 
     Ok(())
 }
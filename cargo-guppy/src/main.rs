@@ -1,11 +1,24 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use cargo_guppy::{Color, FilterOptions, MetadataOptions, OutputStyles};
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(about = "Cargo.lock file analysis")]
 struct Args {
+    /// When to colorize output
+    #[structopt(
+        long,
+        global = true,
+        default_value = "auto",
+        possible_values = &["auto", "always", "never"]
+    )]
+    color: Color,
+    /// Read `cargo metadata` JSON output from this file ('-' for stdin) instead of running cargo
+    #[structopt(long, global = true, parse(from_os_str))]
+    metadata_json: Option<PathBuf>,
     #[structopt(subcommand)]
     cmd: Command,
 }
@@ -25,7 +38,54 @@ enum Command {
     Count,
     #[structopt(name = "dups")]
     /// Print the number of duplicate packages
-    Duplicates,
+    Duplicates {
+        /// Also print a dependency path from the workspace to each version
+        #[structopt(long)]
+        paths: bool,
+    },
+    #[structopt(name = "build-scripts")]
+    /// Print packages that have build scripts
+    BuildScripts {
+        /// Only include packages that are only built for the host platform
+        #[structopt(long)]
+        host_only: bool,
+    },
+    #[structopt(name = "check-dups")]
+    /// Print crates that appear at multiple versions, with a dependency path to each
+    CheckDups {
+        /// Only report crates at semver-incompatible versions, and fail if there are any
+        #[structopt(long)]
+        deny_major: bool,
+    },
+    #[structopt(name = "check-publish")]
+    /// Check that crates publishable to crates.io don't depend on unpublishable path or git crates
+    CheckPublish,
+    #[structopt(name = "fanout")]
+    /// Print packages ranked by their number of direct dependencies
+    Fanout,
+    #[structopt(name = "select")]
+    /// Print the transitive dependencies of workspace members
+    Select {
+        #[structopt(flatten)]
+        filter_opts: FilterOptions,
+        /// Print a dependency link that caused each package to be included
+        #[structopt(long, conflicts_with = "output-ndjson")]
+        explain: bool,
+        /// Print each package as a JSON object on its own line
+        #[structopt(long)]
+        output_ndjson: bool,
+        /// Workspace members to select from, as package ID specs (default: all members)
+        roots: Vec<String>,
+    },
+    #[structopt(name = "summary")]
+    /// Print a summary of the workspace dependency graph
+    Summary,
+    #[structopt(name = "unused-deps")]
+    /// Print declared dependencies of workspace members that aren't resolved (heuristic)
+    UnusedDeps,
+    #[structopt(name = "workspace-tree")]
+    /// Print workspace members as a tree mirroring their directory structure
+    WorkspaceTree,
 }
 
 // When invoked as a cargo subcommand, cargo passes too many arguments so we need to filter out
@@ -44,15 +104,46 @@ fn args() -> impl Iterator<Item = String> {
 
 fn main() {
     let args = Args::from_iter(args());
+    let styles = OutputStyles::new(args.color);
+    let metadata_opts = MetadataOptions::new(args.metadata_json);
 
     let result = match args.cmd {
-        Command::Diff { json, old, new } => cargo_guppy::cmd_diff(json, &old, &new),
-        Command::Count => cargo_guppy::cmd_count(),
-        Command::Duplicates => cargo_guppy::cmd_dups(),
+        Command::Diff { json, old, new } => metadata_opts
+            .reject_metadata_json("diff")
+            .and_then(|()| cargo_guppy::cmd_diff(json, &old, &new)),
+        Command::Count => cargo_guppy::cmd_count(&metadata_opts),
+        Command::Duplicates { paths: true } => cargo_guppy::cmd_dups(&metadata_opts, &styles, true),
+        Command::Duplicates { paths: false } => metadata_opts
+            .reject_metadata_json("dups")
+            .and_then(|()| cargo_guppy::cmd_dups(&metadata_opts, &styles, false)),
+        Command::BuildScripts { host_only } => {
+            cargo_guppy::cmd_build_scripts(&metadata_opts, &styles, host_only)
+        }
+        Command::CheckDups { deny_major } => {
+            cargo_guppy::cmd_check_dups(&metadata_opts, &styles, deny_major)
+        }
+        Command::CheckPublish => cargo_guppy::cmd_check_publish(&metadata_opts, &styles),
+        Command::Fanout => cargo_guppy::cmd_fanout(&metadata_opts, &styles),
+        Command::Select {
+            filter_opts,
+            explain,
+            output_ndjson,
+            roots,
+        } => cargo_guppy::cmd_select(
+            &metadata_opts,
+            &styles,
+            &filter_opts,
+            &roots,
+            explain,
+            output_ndjson,
+        ),
+        Command::Summary => cargo_guppy::cmd_summary(&metadata_opts),
+        Command::UnusedDeps => cargo_guppy::cmd_unused_deps(&metadata_opts),
+        Command::WorkspaceTree => cargo_guppy::cmd_workspace_tree(&metadata_opts, &styles),
     };
 
-    match result {
-        Err(e) => println!("{}\nAborting...", e),
-        Ok(()) => {}
+    if let Err(e) = result {
+        println!("{}\nAborting...", e);
+        std::process::exit(1);
     }
 }
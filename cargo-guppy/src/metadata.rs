@@ -0,0 +1,64 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Options for obtaining the `cargo metadata` output that package graphs are built from.
+
+use cargo_metadata::MetadataCommand;
+use guppy::{graph::PackageGraph, Error};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Where to read `cargo metadata` output from.
+#[derive(Clone, Debug, Default)]
+pub struct MetadataOptions {
+    metadata_json: Option<PathBuf>,
+}
+
+impl MetadataOptions {
+    /// Creates a new `MetadataOptions`.
+    ///
+    /// If `metadata_json` is `None`, `cargo metadata` is run in the current directory. If it is
+    /// `-`, the JSON is read from standard input. Otherwise, it is read from the given file.
+    pub fn new(metadata_json: Option<PathBuf>) -> Self {
+        Self { metadata_json }
+    }
+
+    /// Returns the file `cargo metadata` JSON is read from, if one was specified.
+    pub fn metadata_json(&self) -> Option<&Path> {
+        self.metadata_json.as_deref()
+    }
+
+    /// Returns an error if a metadata JSON file was specified, for commands that only read
+    /// lockfiles and so would otherwise silently ignore it.
+    pub fn reject_metadata_json(&self, command: &str) -> Result<(), Error> {
+        match &self.metadata_json {
+            Some(_) => Err(Error::DepGraphError(format!(
+                "--metadata-json is not supported by '{}', which reads Cargo.lock files",
+                command
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Builds a package graph according to these options.
+    pub fn make_graph(&self) -> Result<PackageGraph, Error> {
+        match &self.metadata_json {
+            None => PackageGraph::from_command(&mut MetadataCommand::new()),
+            Some(path) => {
+                let json = if path.as_os_str() == "-" {
+                    let mut json = vec![];
+                    io::stdin().read_to_end(&mut json)?;
+                    json
+                } else {
+                    fs::read(path)?
+                };
+                let package_graph = PackageGraph::from_json_bytes(&json)?;
+                for warning in package_graph.build_warnings() {
+                    eprintln!("warning: {}", warning);
+                }
+                Ok(package_graph)
+            }
+        }
+    }
+}
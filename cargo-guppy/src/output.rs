@@ -0,0 +1,111 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Shared formatting for command output.
+
+use ansi_term::{Colour, Style};
+use guppy::graph::{PackageMetadata, PackageSource};
+use serde_json::{json, Value};
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+/// When to colorize output.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Color {
+    /// Colorize output if stdout is a terminal and `NO_COLOR` isn't set.
+    Auto,
+    /// Always colorize output.
+    Always,
+    /// Never colorize output.
+    Never,
+}
+
+impl Color {
+    /// Returns true if output should be colorized.
+    pub fn should_colorize(self) -> bool {
+        match self {
+            Color::Auto => env::var_os("NO_COLOR").is_none() && atty::is(atty::Stream::Stdout),
+            Color::Always => true,
+            Color::Never => false,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            other => Err(format!(
+                "unrecognized color '{}' (expected auto, always or never)",
+                other
+            )),
+        }
+    }
+}
+
+/// Styles used to format package names and versions across commands.
+#[derive(Clone, Copy, Debug)]
+pub struct OutputStyles {
+    workspace: Style,
+    third_party: Style,
+    duplicate: Style,
+}
+
+impl OutputStyles {
+    /// Creates a new set of styles. If colorization is disabled, all styles are plain.
+    pub fn new(color: Color) -> Self {
+        if color.should_colorize() {
+            Self {
+                workspace: Colour::Green.normal(),
+                third_party: Colour::Blue.normal(),
+                duplicate: Colour::Yellow.bold(),
+            }
+        } else {
+            Self {
+                workspace: Style::new(),
+                third_party: Style::new(),
+                duplicate: Style::new(),
+            }
+        }
+    }
+
+    /// Formats text that refers to a workspace (path) package.
+    pub fn workspace(&self, text: impl fmt::Display) -> String {
+        self.workspace.paint(text.to_string()).to_string()
+    }
+
+    /// Formats text that refers to a third-party package.
+    pub fn third_party(&self, text: impl fmt::Display) -> String {
+        self.third_party.paint(text.to_string()).to_string()
+    }
+
+    /// Formats text that refers to a duplicated package.
+    pub fn duplicate(&self, text: impl fmt::Display) -> String {
+        self.duplicate.paint(text.to_string()).to_string()
+    }
+}
+
+/// Serializes a package to the JSON object used by commands with JSON output.
+pub fn package_json(package: &PackageMetadata) -> Value {
+    let source = match package.source() {
+        PackageSource::CratesIo => json!({ "kind": "crates-io" }),
+        PackageSource::Registry(url) => json!({ "kind": "registry", "url": url }),
+        PackageSource::Git { url, rev } => json!({ "kind": "git", "url": url, "rev": rev }),
+        PackageSource::Path(path) => json!({ "kind": "path", "path": path }),
+        PackageSource::Other(source) => json!({ "kind": "other", "source": source }),
+    };
+    json!({
+        "id": package.id().repr,
+        "name": package.name(),
+        "version": package.version().to_string(),
+        "source": source,
+        "edition": package.edition(),
+        "license": package.license(),
+        "manifest_path": package.manifest_path(),
+    })
+}
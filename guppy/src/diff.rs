@@ -1,175 +1,319 @@
 // Copyright (c) The cargo-guppy Contributors
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use crate::graph::{DependencyEdge, PackageGraph, PackageMetadata};
 use crate::lockfile::{Lockfile, PackageId};
+use semver::Version;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Default)]
 pub struct DiffOptions;
 
 impl DiffOptions {
-    pub fn diff(&self, old_lockfile: &Lockfile, new_lockfile: &Lockfile) -> Diff {
-        let mut new: HashMap<_, _> = new_lockfile
-            .packages()
-            .map(|p| (p.package_id(), p))
-            .collect();
+    pub fn diff(&self, old_lockfile: &Lockfile, new_lockfile: &Lockfile) -> PackageDiff {
+        let old_ids: Vec<_> = old_lockfile.packages().map(|p| p.package_id()).collect();
+        let new_ids: Vec<_> = new_lockfile.packages().map(|p| p.package_id()).collect();
+        self.diff_ids(&old_ids, &new_ids)
+    }
 
-        let mut removed = old_lockfile
-            .packages()
-            .filter_map(|package| {
-                let pkg_id = package.package_id();
-                if new.remove(&pkg_id).is_none() {
-                    Some(pkg_id)
-                } else {
-                    None
-                }
-            })
+    /// Diffs the packages in two package graphs, for example ones produced by
+    /// `PackageGraph::from_command`.
+    ///
+    /// Packages are identified the same way as in lockfiles: by name, version and source. The source
+    /// includes the kind of source, e.g. `registry+` or `sparse+`, and for git packages, the
+    /// resolved revision.
+    pub fn diff_graphs(&self, old_graph: &PackageGraph, new_graph: &PackageGraph) -> PackageDiff {
+        let old_ids: Vec<_> = old_graph.packages().map(lockfile_package_id).collect();
+        let new_ids: Vec<_> = new_graph.packages().map(lockfile_package_id).collect();
+        self.diff_ids(&old_ids, &new_ids)
+    }
+
+    fn diff_ids(&self, old_ids: &[PackageId], new_ids: &[PackageId]) -> PackageDiff {
+        let mut new: HashSet<_> = new_ids.iter().collect();
+
+        let mut removed = old_ids
+            .iter()
+            .filter(|pkg_id| !new.remove(pkg_id))
             .map(|removed_pkg_id| {
-                let remaining_packages = new_lockfile
-                    .packages()
-                    .filter_map(|package| {
-                        let pkg_id = package.package_id();
-                        if (pkg_id != removed_pkg_id) && (pkg_id.name() == removed_pkg_id.name()) {
-                            Some(pkg_id)
-                        } else {
-                            None
-                        }
+                let remaining_packages = new_ids
+                    .iter()
+                    .filter(|pkg_id| {
+                        (*pkg_id != removed_pkg_id) && (pkg_id.name() == removed_pkg_id.name())
                     })
+                    .cloned()
                     .collect::<Vec<_>>();
 
-                if remaining_packages.is_empty() {
-                    (removed_pkg_id.clone(), None)
-                } else {
-                    (removed_pkg_id.clone(), Some(remaining_packages))
-                }
+                (removed_pkg_id.clone(), remaining_packages)
             })
             .collect::<HashMap<_, _>>();
 
         let mut added = new
             .into_iter()
-            .map(|(added_pkg_id, _pkg)| {
-                let existing_packages = new_lockfile
-                    .packages()
-                    .filter_map(|package| {
-                        let pkg_id = package.package_id();
-                        if (pkg_id != added_pkg_id) && (package.name() == added_pkg_id.name()) {
-                            Some(pkg_id)
-                        } else {
-                            None
-                        }
+            .map(|added_pkg_id| {
+                let existing_packages = new_ids
+                    .iter()
+                    .filter(|pkg_id| {
+                        (*pkg_id != added_pkg_id) && (pkg_id.name() == added_pkg_id.name())
                     })
+                    .cloned()
                     .collect::<Vec<_>>();
 
-                if existing_packages.is_empty() {
-                    (added_pkg_id.clone(), None)
-                } else {
-                    (added_pkg_id.clone(), Some(existing_packages))
-                }
+                (added_pkg_id.clone(), existing_packages)
             })
             .collect::<HashMap<_, _>>();
 
-        let mut updated = removed
-            .iter()
-            .filter_map(|(removed_pkg_id, _)| {
-                if let Some((updated, _)) = added
-                    .iter()
-                    .find(|added_pkg| removed_pkg_id.name() == added_pkg.0.name())
-                {
-                    Some((removed_pkg_id.clone(), updated.clone()))
-                } else {
-                    None
-                }
+        let mut changed = removed
+            .keys()
+            .filter_map(|removed_pkg_id| {
+                // If there are several candidates, prefer the one with the same version so that
+                // [patch] and [replace] overrides are detected, then pick one deterministically.
+                added
+                    .keys()
+                    .filter(|added_pkg_id| removed_pkg_id.name() == added_pkg_id.name())
+                    .min_by_key(|added_pkg_id| {
+                        (
+                            added_pkg_id.version() != removed_pkg_id.version(),
+                            added_pkg_id.version(),
+                            added_pkg_id.source(),
+                        )
+                    })
+                    .map(|added_pkg_id| ChangedPackage::new(removed_pkg_id, added_pkg_id))
             })
             .collect::<Vec<_>>();
-        updated.sort_by(|a, b| a.0.name().cmp(b.0.name()));
+        changed.sort_by(|a, b| package_key(&a.old).cmp(&package_key(&b.old)));
 
         // Remove entries from Added and Removed
-        for (removed_pkg_id, added_pkg_id) in &updated {
-            removed.remove(removed_pkg_id);
-            added.remove(added_pkg_id);
+        for changed_pkg in &changed {
+            removed.remove(&changed_pkg.old);
+            added.remove(&changed_pkg.new);
         }
 
-        let mut removed = removed.into_iter().collect::<Vec<_>>();
-        removed.sort_by(|(a, _), (b, _)| a.name().cmp(b.name()));
-        let mut added = added.into_iter().collect::<Vec<_>>();
-        added.sort_by(|(a, _), (b, _)| a.name().cmp(b.name()));
-
-        Diff {
-            updated,
-            removed,
-            added,
+        PackageDiff {
+            added: into_sorted_entries(added),
+            removed: into_sorted_entries(removed),
+            changed,
         }
     }
 }
 
+/// Converts a package in a graph to the way it would be identified in a lockfile.
+pub(crate) fn lockfile_package_id(package: &PackageMetadata) -> PackageId {
+    PackageId::new(
+        package.name().to_string(),
+        package.version().to_string(),
+        package.source_repr().map(|source| source.to_string()),
+    )
+}
+
+/// The result of diffing two lockfiles or package graphs.
+///
+/// Each list is sorted by package name, then by version and source.
 #[derive(Debug, Serialize)]
-pub struct Diff {
-    updated: Vec<(PackageId, PackageId)>,
-    removed: Vec<(PackageId, Option<Vec<PackageId>>)>,
-    added: Vec<(PackageId, Option<Vec<PackageId>>)>,
+pub struct PackageDiff {
+    /// Packages that are only present in the new lockfile or graph.
+    pub added: Vec<DiffEntry>,
+    /// Packages that are only present in the old lockfile or graph.
+    pub removed: Vec<DiffEntry>,
+    /// Packages that are present in both, but at different versions or sources.
+    pub changed: Vec<ChangedPackage>,
 }
 
-impl ::std::fmt::Display for Diff {
-    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-        fn write_dups(
-            f: &mut ::std::fmt::Formatter<'_>,
-            dups: &Option<Vec<PackageId>>,
-        ) -> ::std::fmt::Result {
-            if let Some(dups) = dups {
-                write!(f, " ({}", dups[0].version())?;
-                for p in &dups[1..] {
-                    write!(f, ", {}", p.version())?;
-                }
-                write!(f, ")")?;
-            }
+impl PackageDiff {
+    /// Returns true if no packages were added, removed or changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
 
-            Ok(())
-        }
+/// The result of diffing two package graphs with `PackageGraph::diff`.
+///
+/// Links are sorted by the name, version and source of the `from` package, then of the `to`
+/// package.
+#[derive(Debug, Serialize)]
+pub struct GraphDiff {
+    /// The packages that were added, removed or changed.
+    pub packages: PackageDiff,
+    /// Dependency links that are only present in the new graph, as `(from, to)` pairs.
+    pub added_links: Vec<(PackageId, PackageId)>,
+    /// Dependency links that are only present in the old graph, as `(from, to)` pairs.
+    pub removed_links: Vec<(PackageId, PackageId)>,
+    /// Dependency links that are present in both graphs but have different dependency kinds or
+    /// requirements.
+    pub changed_links: Vec<ChangedLink>,
+}
 
-        if !self.added.is_empty() {
-            writeln!(f, "Added Packages (Duplicate versions in '()'):")?;
-            for (added, dups) in &self.added {
-                write!(f, "\t{} {}", added.name(), added.version(),)?;
+impl GraphDiff {
+    /// Returns true if no packages or links were added, removed or changed.
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+            && self.added_links.is_empty()
+            && self.removed_links.is_empty()
+            && self.changed_links.is_empty()
+    }
+}
 
-                write_dups(f, dups)?;
-                writeln!(f)?;
-            }
-            writeln!(f)?;
+/// A dependency link that is present in both graphs diffed by `PackageGraph::diff`, but was
+/// changed.
+#[derive(Debug, Serialize)]
+pub struct ChangedLink {
+    /// The package on the `from` end of the link.
+    pub from: PackageId,
+    /// The package on the `to` end of the link.
+    pub to: PackageId,
+    /// The dependency kinds (`normal`, `build` and `dev`) of the link in the old graph.
+    pub old_kinds: Vec<&'static str>,
+    /// The dependency kinds of the link in the new graph.
+    pub new_kinds: Vec<&'static str>,
+    /// The link in the old graph.
+    #[serde(skip)]
+    pub old: DependencyEdge,
+    /// The link in the new graph.
+    #[serde(skip)]
+    pub new: DependencyEdge,
+}
+
+/// A package that was added or removed.
+#[derive(Debug, Serialize)]
+pub struct DiffEntry {
+    /// The package that was added or removed.
+    pub package_id: PackageId,
+    /// Other versions of this package that are present in the new lockfile or graph.
+    pub other_versions: Vec<PackageId>,
+}
+
+/// A package that is present in both the old and new inputs, but was changed.
+#[derive(Debug, Serialize)]
+pub struct ChangedPackage {
+    /// The old package ID.
+    pub old: PackageId,
+    /// The new package ID.
+    pub new: PackageId,
+    /// How the package was changed.
+    pub kind: ChangeKind,
+}
+
+impl ChangedPackage {
+    fn new(old: &PackageId, new: &PackageId) -> Self {
+        Self {
+            old: old.clone(),
+            new: new.clone(),
+            kind: ChangeKind::classify(old, new),
         }
+    }
+}
 
-        if !self.removed.is_empty() {
-            writeln!(f, "Removed Packages (Remaining versions in '()'):")?;
-            for (removed, dups) in &self.removed {
-                write!(f, "\t{} {}", removed.name(), removed.version(),)?;
+/// The kind of change made to a package.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    /// The version changed in a semver-incompatible way: the major version changed, or for
+    /// pre-1.0 versions, the leftmost non-zero component did (e.g. 0.6.5 to 0.7.0, or 0.0.1 to
+    /// 0.0.2).
+    ///
+    /// This is also used if either version could not be parsed.
+    Major,
+    /// The minor version component changed in a semver-compatible way.
+    Minor,
+    /// The patch version component (or pre-release or build metadata) changed.
+    Patch,
+    /// The package now comes from a different source, e.g. a git repository instead of a
+    /// registry, or a different revision of the same git repository. This takes precedence over
+    /// any version change.
+    SourceChanged,
+    /// The package now comes from a different kind of source (registry, git or path), but its
+    /// version is the same.
+    ///
+    /// This is typically caused by adding or removing a `[patch]` or `[replace]` override, and is
+    /// reported separately since it is easy to miss in review.
+    Patched,
+}
 
-                write_dups(f, dups)?;
-                writeln!(f)?;
-            }
-            writeln!(f)?;
+impl ChangeKind {
+    fn classify(old: &PackageId, new: &PackageId) -> Self {
+        if old.source() != new.source() {
+            let same_kind = source_kind(old.source()) == source_kind(new.source());
+            return if old.version() == new.version() && !same_kind {
+                ChangeKind::Patched
+            } else {
+                ChangeKind::SourceChanged
+            };
         }
 
-        if !self.updated.is_empty() {
-            writeln!(f, "Updated Packages:")?;
-            for (removed, added) in &self.updated {
-                writeln!(
-                    f,
-                    "\t{}: {} -> {}",
-                    removed.name(),
-                    removed.version(),
-                    added.version(),
-                )?;
+        match (Version::parse(old.version()), Version::parse(new.version())) {
+            (Ok(old), Ok(new)) => {
+                if compat_version(&old) != compat_version(&new) {
+                    ChangeKind::Major
+                } else if old.minor != new.minor {
+                    ChangeKind::Minor
+                } else {
+                    ChangeKind::Patch
+                }
             }
-            writeln!(f)?;
+            _ => ChangeKind::Major,
         }
+    }
 
-        Ok(())
+    /// Returns a short, human-readable name for this kind of change.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Major => "major",
+            ChangeKind::Minor => "minor",
+            ChangeKind::Patch => "patch",
+            ChangeKind::SourceChanged => "source changed",
+            ChangeKind::Patched => "patched",
+        }
     }
 }
 
+/// Returns the kind of a lockfile source, treating `registry+` and `sparse+` sources as the same
+/// kind. Packages without a source are path dependencies.
+fn source_kind(source: Option<&str>) -> Option<&str> {
+    let source = source?;
+    match source.find('+').map(|idx| &source[..idx]) {
+        Some("sparse") => Some("registry"),
+        Some(kind) => Some(kind),
+        None => Some(source),
+    }
+}
+
+/// Returns the part of a version that Cargo considers for semver compatibility: the major version,
+/// or the first nonzero component for pre-1.0 versions.
+///
+/// Two versions are semver-compatible if and only if this returns the same value for both.
+pub fn compat_version(version: &Version) -> (u64, u64, u64) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+fn into_sorted_entries(entries: HashMap<PackageId, Vec<PackageId>>) -> Vec<DiffEntry> {
+    let mut entries = entries
+        .into_iter()
+        .map(|(package_id, other_versions)| DiffEntry {
+            package_id,
+            other_versions,
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| package_key(&a.package_id).cmp(&package_key(&b.package_id)));
+    entries
+}
+
+pub(crate) type PackageKey<'a> = (&'a str, &'a str, Option<&'a str>);
+
+/// Returns the key packages are sorted by: name, then version, then source.
+pub(crate) fn package_key(package_id: &PackageId) -> PackageKey<'_> {
+    (package_id.name(), package_id.version(), package_id.source())
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{diff::DiffOptions, lockfile::Lockfile};
+    use crate::{
+        diff::{ChangeKind, DiffOptions},
+        lockfile::{Lockfile, PackageId},
+    };
 
     #[test]
     fn simple_diff() {
@@ -272,6 +416,239 @@ mod tests {
 
         let diff = DiffOptions::default().diff(&old, &new);
 
+        let added: Vec<_> = diff
+            .added
+            .iter()
+            .map(|entry| entry.package_id.name())
+            .collect();
+        assert_eq!(
+            added,
+            vec!["proc-macro2", "quote", "serde_derive", "syn", "unicode-xid"]
+        );
+        let removed: Vec<_> = diff
+            .removed
+            .iter()
+            .map(|entry| entry.package_id.name())
+            .collect();
+        assert_eq!(removed, vec!["toml"]);
+        assert!(diff.changed.is_empty());
+
         serde_json::to_string(&diff).unwrap();
     }
+
+    #[test]
+    fn change_kinds() {
+        let old = r#"
+            [[package]]
+            name = "bitflags"
+            version = "1.1.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "libc"
+            version = "0.2.62"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "quote"
+            version = "1.0.2"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "rand"
+            version = "0.6.5"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "syn"
+            version = "0.15.44"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "tinyvec"
+            version = "0.0.1"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "walkdir"
+            version = "2.2.9"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [metadata]
+        "#;
+
+        let new = r#"
+            [[package]]
+            name = "bitflags"
+            version = "1.2.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "libc"
+            version = "0.2.63"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "quote"
+            version = "1.0.3"
+            source = "git+https://github.com/dtolnay/quote#7a2b1b2c6c3e1b4e5a9a9a3e1d1c3b7e7e2c5a3d"
+
+            [[package]]
+            name = "rand"
+            version = "0.7.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "syn"
+            version = "1.0.5"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "tinyvec"
+            version = "0.0.2"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "walkdir"
+            version = "2.2.9"
+            source = "git+https://github.com/BurntSushi/walkdir.git?tag=2.2.9#7c7013259eb9db400b3e5c7bc60330ca08068826"
+
+            [metadata]
+        "#;
+
+        let old = Lockfile::from_str(old).unwrap();
+        let new = Lockfile::from_str(new).unwrap();
+
+        let diff = DiffOptions.diff(&old, &new);
+        let kinds: Vec<_> = diff
+            .changed
+            .iter()
+            .map(|changed| (changed.old.name(), changed.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("bitflags", ChangeKind::Minor),
+                ("libc", ChangeKind::Patch),
+                ("quote", ChangeKind::SourceChanged),
+                ("rand", ChangeKind::Major),
+                ("syn", ChangeKind::Major),
+                ("tinyvec", ChangeKind::Major),
+                ("walkdir", ChangeKind::Patched),
+            ]
+        );
+    }
+
+    #[test]
+    fn sorted_entries() {
+        let old = r#"
+            [[package]]
+            name = "libc"
+            version = "0.2.62"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [metadata]
+        "#;
+
+        let new = r#"
+            [[package]]
+            name = "rand"
+            version = "0.7.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "rand"
+            version = "0.6.5"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "rand"
+            version = "0.6.5"
+            source = "git+https://github.com/rust-random/rand#7c7013259eb9db400b3e5c7bc60330ca08068826"
+
+            [[package]]
+            name = "bitflags"
+            version = "1.2.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [metadata]
+        "#;
+
+        let old = Lockfile::from_str(old).unwrap();
+        let new = Lockfile::from_str(new).unwrap();
+
+        let diff = DiffOptions.diff(&old, &new);
+        let added: Vec<_> = diff
+            .added
+            .iter()
+            .map(|entry| {
+                let package_id = &entry.package_id;
+                (package_id.name(), package_id.version(), package_id.source())
+            })
+            .collect();
+        assert_eq!(
+            added,
+            vec![
+                (
+                    "bitflags",
+                    "1.2.0",
+                    Some("registry+https://github.com/rust-lang/crates.io-index")
+                ),
+                (
+                    "rand",
+                    "0.6.5",
+                    Some("git+https://github.com/rust-random/rand")
+                ),
+                (
+                    "rand",
+                    "0.6.5",
+                    Some("registry+https://github.com/rust-lang/crates.io-index")
+                ),
+                (
+                    "rand",
+                    "0.7.0",
+                    Some("registry+https://github.com/rust-lang/crates.io-index")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn classify_sources() {
+        let id = |source: Option<&str>| {
+            PackageId::new(
+                "region".to_string(),
+                "2.1.2".to_string(),
+                source.map(|source| source.to_string()),
+            )
+        };
+        let crates_io = id(Some(
+            "registry+https://github.com/rust-lang/crates.io-index",
+        ));
+        let sparse = id(Some("sparse+https://index.crates.io/"));
+        let git_old = id(Some(
+            "git+https://github.com/darfink/region-rs#1111111111111111111111111111111111111111",
+        ));
+        let git_new = id(Some(
+            "git+https://github.com/darfink/region-rs#2222222222222222222222222222222222222222",
+        ));
+        let path = id(None);
+
+        let cases = vec![
+            (&crates_io, &git_old, ChangeKind::Patched),
+            (&git_old, &path, ChangeKind::Patched),
+            (&path, &crates_io, ChangeKind::Patched),
+            (&git_old, &git_new, ChangeKind::SourceChanged),
+            (&crates_io, &sparse, ChangeKind::SourceChanged),
+        ];
+        for (old, new, expected) in cases {
+            assert_eq!(
+                ChangeKind::classify(old, new),
+                expected,
+                "{:?} -> {:?}",
+                old.source(),
+                new.source()
+            );
+        }
+    }
 }
@@ -16,11 +16,14 @@ pub enum Error {
     ConfigIoError(io::Error),
     ConfigParseError(toml::de::Error),
     CommandError(MetadataError),
-    MetadataParseError(serde_json::Error),
+    /// An error while parsing `cargo metadata` JSON output, along with the ID of the package
+    /// closest to (and before) the error, if one could be found.
+    MetadataParseError(serde_json::Error, Option<MetadataPackageId>),
     DepGraphError(String),
     DepGraphUnknownPackageId(MetadataPackageId),
     DepGraphInternalError(String),
     PackageIdParseError(MetadataPackageId, String),
+    RegistryIndexError(String),
 }
 
 impl From<io::Error> for Error {
@@ -37,15 +40,21 @@ impl fmt::Display for Error {
             ConfigIoError(err) => write!(f, "Error while reading config file: {}", err),
             ConfigParseError(err) => write!(f, "Error while parsing config file: {}", err),
             CommandError(err) => write!(f, "Error while executing 'cargo metadata': {}", err),
-            MetadataParseError(err) => write!(
+            MetadataParseError(err, None) => write!(
                 f,
                 "Error while parsing 'cargo metadata' JSON output: {}",
                 err
             ),
+            MetadataParseError(err, Some(id)) => write!(
+                f,
+                "Error while parsing 'cargo metadata' JSON output (near package '{}'): {}",
+                id, err
+            ),
             DepGraphError(msg) => write!(f, "Error while computing dependency graph: {}", msg),
             DepGraphUnknownPackageId(id) => write!(f, "Unknown package ID: {}", id),
             DepGraphInternalError(msg) => write!(f, "Internal error in dependency graph: {}", msg),
             PackageIdParseError(id, msg) => write!(f, "Error parsing package ID '{}': {}", id, msg),
+            RegistryIndexError(msg) => write!(f, "Error while reading registry index: {}", msg),
         }
     }
 }
@@ -57,12 +66,13 @@ impl error::Error for Error {
             LockfileParseError(err) => Some(err),
             ConfigIoError(err) => Some(err),
             ConfigParseError(err) => Some(err),
-            MetadataParseError(err) => Some(err),
+            MetadataParseError(err, _) => Some(err),
             CommandError(_) => None,
             DepGraphError(_) => None,
             DepGraphUnknownPackageId(_) => None,
             DepGraphInternalError(_) => None,
             PackageIdParseError(_, _) => None,
+            RegistryIndexError(_) => None,
         }
     }
 }
@@ -2,18 +2,27 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::errors::Error;
+use crate::graph::graph::direct_third_party_set;
 use crate::graph::{
     kind_str, DependencyEdge, DependencyMetadata, PackageGraph, PackageGraphData, PackageMetadata,
-    Workspace,
+    PackageSource, Workspace,
 };
-use cargo_metadata::{Dependency, DependencyKind, Metadata, NodeDep, Package, PackageId, Resolve};
+use cargo_metadata::{
+    Dependency, DependencyKind, Metadata, NodeDep, Package, PackageId, Resolve, Source,
+};
+use fixedbitset::FixedBitSet;
 use petgraph::prelude::*;
 use semver::Version;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde_json::{Map, Value};
 use std::collections::{BTreeMap, HashMap, HashSet};
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 impl PackageGraph {
     /// Constructs a new `PackageGraph` instances from the given metadata.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub(crate) fn build(metadata: Metadata) -> Result<Self, Error> {
         let resolve = metadata.resolve.ok_or_else(|| {
             Error::DepGraphError(
@@ -25,28 +34,189 @@ impl PackageGraph {
 
         let mut build_state = GraphBuildState::new(&metadata.packages, resolve, &workspace_members);
 
-        let packages: HashMap<_, _> = metadata
-            .packages
-            .into_iter()
-            .map(|package| build_state.process_package(package))
-            .collect::<Result<_, _>>()?;
+        let packages = build_state.process_packages(metadata.packages)?;
 
         let dep_graph = build_state.finish();
 
         let workspace = Workspace::new(metadata.workspace_root, &packages, workspace_members)?;
 
+        let mut workspace_set = FixedBitSet::with_capacity(dep_graph.node_count());
+        for package in packages.values().filter(|package| package.in_workspace) {
+            workspace_set.insert(package.node_idx.index());
+        }
+        let direct_third_party_set = direct_third_party_set(&dep_graph, &workspace_set);
+
         Ok(Self {
             dep_graph,
+            workspace_set,
+            direct_third_party_set,
             data: PackageGraphData {
                 packages,
                 workspace,
             },
+            build_warnings: vec![],
+        })
+    }
+}
+
+/// A non-critical field that was missing from `cargo metadata` output, and that was filled in with
+/// a default value while constructing a package graph.
+///
+/// Older versions of Cargo and hand-written metadata may omit some fields. Returned by
+/// `PackageGraph::build_warnings`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildWarning {
+    package_id: PackageId,
+    field: String,
+    default: &'static str,
+}
+
+impl BuildWarning {
+    /// Returns the ID of the package the field was missing from.
+    pub fn package_id(&self) -> &PackageId {
+        &self.package_id
+    }
+
+    /// Returns the name of the missing field. Fields of dependencies are of the form
+    /// `dependencies.<name>.<field>`.
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the default value that was used, as JSON.
+    pub fn default(&self) -> &'static str {
+        self.default
+    }
+}
+
+impl fmt::Display for BuildWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: missing field '{}', defaulting to {}",
+            self.package_id, self.field, self.default
+        )
+    }
+}
+
+/// Package fields that `cargo_metadata` fills in with defaults if they're missing, along with the
+/// defaults it uses.
+const DEFAULTED_FIELDS: &[(&str, &str)] = &[
+    ("edition", "\"2015\""),
+    ("authors", "[]"),
+    ("categories", "[]"),
+    ("keywords", "[]"),
+];
+
+/// Returns a warning for each field in `DEFAULTED_FIELDS` that's missing from the packages in
+/// `cargo metadata` JSON output.
+///
+/// `metadata` must have been parsed from `json`. Only the keys of each package are looked at, so
+/// this is much cheaper than parsing the JSON into a `Value`.
+pub(super) fn defaulted_field_warnings(json: &[u8], metadata: &Metadata) -> Vec<BuildWarning> {
+    #[derive(Deserialize)]
+    struct PackageKeys {
+        packages: Vec<HashMap<String, IgnoredAny>>,
+    }
+
+    let keys = match serde_json::from_slice::<PackageKeys>(json) {
+        Ok(keys) => keys,
+        Err(_) => return vec![],
+    };
+    keys.packages
+        .iter()
+        .zip(&metadata.packages)
+        .flat_map(|(keys, package)| {
+            DEFAULTED_FIELDS
+                .iter()
+                .filter(move |(field, _)| !keys.contains_key(*field))
+                .map(move |(field, default)| BuildWarning {
+                    package_id: package.id.clone(),
+                    field: field.to_string(),
+                    default,
+                })
         })
+        .collect()
+}
+
+/// Fills in defaults for non-critical fields missing from the packages in `cargo metadata` JSON
+/// output, returning a warning for each one.
+///
+/// Fields in `DEFAULTED_FIELDS` are already defaulted by `cargo_metadata`, so they're only warned
+/// about. Fields that the graph can't be built without, such as `id` or `dependencies`, are left
+/// alone.
+pub(super) fn fill_missing_fields(metadata: &mut Value) -> Vec<BuildWarning> {
+    fn fill(
+        object: &mut Map<String, Value>,
+        field: &str,
+        default: &'static str,
+        mut warn: impl FnMut(String, &'static str),
+    ) {
+        if !object.contains_key(field) {
+            object.insert(
+                field.to_string(),
+                serde_json::from_str(default).expect("valid default"),
+            );
+            warn(field.to_string(), default);
+        }
+    }
+
+    let mut warnings = vec![];
+    let packages = match metadata.get_mut("packages").and_then(Value::as_array_mut) {
+        Some(packages) => packages,
+        None => return warnings,
+    };
+    for package in packages.iter_mut().filter_map(Value::as_object_mut) {
+        let package_id = match package.get("id").and_then(Value::as_str) {
+            Some(id) => PackageId {
+                repr: id.to_string(),
+            },
+            None => continue,
+        };
+        let mut warn = |field, default| {
+            warnings.push(BuildWarning {
+                package_id: package_id.clone(),
+                field,
+                default,
+            })
+        };
+
+        fill(package, "features", "{}", &mut warn);
+        fill(package, "targets", "[]", &mut warn);
+        for (field, default) in DEFAULTED_FIELDS {
+            if !package.contains_key(*field) {
+                warn(field.to_string(), default);
+            }
+        }
+
+        let deps = package
+            .get_mut("dependencies")
+            .and_then(Value::as_array_mut)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_object_mut);
+        for dep in deps {
+            let name = dep
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string();
+            let mut dep_warn =
+                |field, default| warn(format!("dependencies.{}.{}", name, field), default);
+            // A null kind means a normal dependency.
+            fill(dep, "kind", "null", &mut dep_warn);
+            fill(dep, "optional", "false", &mut dep_warn);
+            fill(dep, "uses_default_features", "true", &mut dep_warn);
+            fill(dep, "features", "[]", &mut dep_warn);
+        }
     }
+
+    warnings
 }
 
 impl Workspace {
     /// Indexes and creates a new workspace.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     fn new(
         workspace_root: impl Into<PathBuf>,
         packages: &HashMap<PackageId, PackageMetadata>,
@@ -86,6 +256,57 @@ impl Workspace {
     }
 }
 
+impl PackageSource {
+    /// Parses the source of a package from its metadata.
+    fn new(id: &PackageId, source: Option<&Source>, manifest_path: &Path) -> Result<Self, Error> {
+        let source = match source {
+            Some(source) => source,
+            None => {
+                // Path dependencies don't have a source.
+                let dir = manifest_path.parent().ok_or_else(|| {
+                    Error::DepGraphError(format!(
+                        "package '{}' has invalid manifest path {:?}",
+                        id, manifest_path
+                    ))
+                })?;
+                return Ok(PackageSource::Path(dir.to_path_buf()));
+            }
+        };
+
+        let source_str = source.to_string();
+        if source_str == CRATES_IO_GIT_INDEX || source_str == CRATES_IO_SPARSE_INDEX {
+            return Ok(PackageSource::CratesIo);
+        }
+
+        let other = || PackageSource::Other(source_str.clone());
+        let plus_idx = match source_str.find('+') {
+            Some(plus_idx) => plus_idx,
+            None => return Ok(other()),
+        };
+        let (kind, url) = (&source_str[..plus_idx], &source_str[plus_idx + 1..]);
+        match kind {
+            "registry" | "sparse" => Ok(PackageSource::Registry(url.to_string())),
+            "git" => {
+                // The resolved revision is specified after the last '#'.
+                match url.rfind('#') {
+                    Some(hash_idx) => Ok(PackageSource::Git {
+                        url: url[..hash_idx].to_string(),
+                        rev: url[hash_idx + 1..].to_string(),
+                    }),
+                    None => Ok(other()),
+                }
+            }
+            _ => Ok(other()),
+        }
+    }
+}
+
+/// The source of crates.io packages, through the git index.
+const CRATES_IO_GIT_INDEX: &str = "registry+https://github.com/rust-lang/crates.io-index";
+/// The source of crates.io packages, through the sparse index that Cargo uses by default since
+/// Rust 1.70.
+const CRATES_IO_SPARSE_INDEX: &str = "sparse+https://index.crates.io/";
+
 /// Helper struct for building up dependency graph.
 struct GraphBuildState<'a> {
     dep_graph: Graph<PackageId, DependencyEdge>,
@@ -96,6 +317,11 @@ struct GraphBuildState<'a> {
 }
 
 impl<'a> GraphBuildState<'a> {
+    /// Creates the build state, adding a node for every package.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "create_nodes", level = "debug", skip_all)
+    )]
     fn new<'b>(
         packages: impl IntoIterator<Item = &'b Package>,
         resolve: Resolve,
@@ -127,6 +353,21 @@ impl<'a> GraphBuildState<'a> {
         }
     }
 
+    /// Processes every package, adding its dependency edges and creating its metadata.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "create_edges", level = "debug", skip_all)
+    )]
+    fn process_packages(
+        &mut self,
+        packages: Vec<Package>,
+    ) -> Result<HashMap<PackageId, PackageMetadata>, Error> {
+        packages
+            .into_iter()
+            .map(|package| self.process_package(package))
+            .collect()
+    }
+
     fn process_package(&mut self, package: Package) -> Result<(PackageId, PackageMetadata), Error> {
         let (node_idx, _, _) = self.package_data(&package.id)?;
         let in_workspace = self.workspace_members.contains(&package.id);
@@ -138,6 +379,14 @@ impl<'a> GraphBuildState<'a> {
                 ))
             })?;
 
+        let source =
+            PackageSource::new(&package.id, package.source.as_ref(), &package.manifest_path)?;
+
+        let has_build_script = package
+            .targets
+            .iter()
+            .any(|target| target.kind.iter().any(|kind| kind == "custom-build"));
+
         let dep_resolver =
             DependencyResolver::new(&package.id, &self.package_data, &package.dependencies);
 
@@ -148,8 +397,8 @@ impl<'a> GraphBuildState<'a> {
         } in &resolved_deps
         {
             let (name, deps) = dep_resolver.resolve(resolved_name, pkg)?;
-            let (dep_idx, _, _) = self.package_data(pkg)?;
-            let edge = DependencyEdge::new(&package.id, name, resolved_name, deps)?;
+            let (dep_idx, package_name, _) = self.package_data(pkg)?;
+            let edge = DependencyEdge::new(&package.id, name, resolved_name, package_name, deps)?;
             // Use update_edge instead of add_edge to prevent multiple edges from being added
             // between these two nodes.
             // XXX maybe check for an existing edge?
@@ -167,6 +416,12 @@ impl<'a> GraphBuildState<'a> {
                 license: package.license,
                 deps: package.dependencies,
                 manifest_path: package.manifest_path,
+                source,
+                source_repr: package.source.map(|source| source.to_string()),
+                edition: package.edition,
+                features: package.features,
+                has_build_script,
+                publish: package.publish,
 
                 node_idx,
                 in_workspace,
@@ -314,6 +569,7 @@ impl DependencyEdge {
         from_id: &PackageId,
         name: &str,
         resolved_name: &str,
+        package_name: &str,
         deps: &[&Dependency],
     ) -> Result<Self, Error> {
         // deps should have at most 1 normal dependency, 1 build dep and 1 dev dep.
@@ -383,6 +639,7 @@ impl DependencyEdge {
         Ok(DependencyEdge {
             dep_name: name.into(),
             resolved_name: resolved_name.into(),
+            package_name: package_name.into(),
             normal,
             build,
             dev,
@@ -0,0 +1,134 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::graph::{DependencyDirection, PackageGraph};
+use cargo_metadata::PackageId;
+use petgraph::algo::tarjan_scc;
+use petgraph::prelude::*;
+use std::collections::HashMap;
+
+/// ## Depths
+///
+/// The methods in this section compute the *depth* of packages, which is useful for e.g. build
+/// parallelism analysis.
+impl PackageGraph {
+    /// Returns the depth of every package in this graph in the specified direction.
+    ///
+    /// The depth of a package is the length of the longest path to it from a *root* package, i.e.
+    /// a package that has no incoming links in the specified direction. Roots have a depth of 0.
+    ///
+    /// * If direction is Forward, roots are packages that nothing else depends on (typically
+    ///   workspace members), and the depth of a package is the longest chain of dependencies from
+    ///   a root to it.
+    /// * If direction is Reverse, roots are packages that have no dependencies, and the depth of a
+    ///   package is the longest chain of dependencies from it to a leaf.
+    ///
+    /// Depths are computed over the graph's strongly connected components, so packages within a
+    /// cycle share a depth and this always terminates.
+    pub fn depths(&self, direction: DependencyDirection) -> HashMap<&PackageId, usize> {
+        let dep_graph = self.dep_graph();
+        let scc_depths = self.scc_depths(direction);
+
+        dep_graph
+            .node_indices()
+            .map(|node_idx| {
+                let scc_idx = scc_depths.scc_idxs[&node_idx];
+                (&dep_graph[node_idx], scc_depths.depths[scc_idx])
+            })
+            .collect()
+    }
+
+    /// Returns one of the longest chains of packages in this graph in the specified direction,
+    /// starting from a root.
+    ///
+    /// If direction is Forward, the chain starts at a package that nothing else depends on and
+    /// each package depends on the next one. If direction is Reverse, the chain is listed in the
+    /// opposite order. The chain has one more package than the maximum depth returned by
+    /// `depths`, and is empty if the graph is.
+    ///
+    /// Like `depths`, this works over the graph's strongly connected components, and lists one
+    /// package for every component along the chain. Consecutive packages are directly linked
+    /// unless the chain passes through a cycle.
+    pub fn longest_chain(&self, direction: DependencyDirection) -> Vec<&PackageId> {
+        let dep_graph = self.dep_graph();
+        let scc_depths = self.scc_depths(direction);
+
+        let deepest =
+            (0..scc_depths.depths.len()).max_by_key(|scc_idx| scc_depths.depths[*scc_idx]);
+        let mut scc_idx = match deepest {
+            Some(scc_idx) => scc_idx,
+            None => return vec![],
+        };
+
+        // Walk back towards the root, recording the package the chain enters each component at.
+        let mut node_idx = scc_depths.sccs[scc_idx][0];
+        let mut chain = vec![];
+        loop {
+            match scc_depths.preds[scc_idx] {
+                Some((pred_idx, entry_idx)) => {
+                    chain.push(&dep_graph[entry_idx]);
+                    node_idx = pred_idx;
+                    scc_idx = scc_depths.scc_idxs[&pred_idx];
+                }
+                None => {
+                    chain.push(&dep_graph[node_idx]);
+                    break;
+                }
+            }
+        }
+        chain.reverse();
+        chain
+    }
+
+    fn scc_depths(&self, direction: DependencyDirection) -> SccDepths {
+        let dep_graph = self.dep_graph();
+
+        // tarjan_scc returns SCCs in reverse topological order. Iterate over them such that every
+        // SCC is visited after all the SCCs it has incoming links from.
+        let mut sccs = tarjan_scc(dep_graph);
+        if direction == DependencyDirection::Forward {
+            sccs.reverse();
+        }
+        let incoming = direction.opposite().to_direction();
+
+        let mut scc_idxs = HashMap::with_capacity(dep_graph.node_count());
+        for (scc_idx, scc) in sccs.iter().enumerate() {
+            scc_idxs.extend(scc.iter().map(|node_idx| (*node_idx, scc_idx)));
+        }
+
+        let mut depths = vec![0; sccs.len()];
+        let mut preds = vec![None; sccs.len()];
+        for (scc_idx, scc) in sccs.iter().enumerate() {
+            let deepest_pred = scc
+                .iter()
+                .flat_map(|node_idx| {
+                    dep_graph
+                        .neighbors_directed(*node_idx, incoming)
+                        .map(move |neighbor_idx| (neighbor_idx, *node_idx))
+                })
+                .filter(|(neighbor_idx, _)| scc_idxs[neighbor_idx] != scc_idx)
+                .max_by_key(|(neighbor_idx, _)| depths[scc_idxs[neighbor_idx]]);
+            if let Some((neighbor_idx, node_idx)) = deepest_pred {
+                depths[scc_idx] = depths[scc_idxs[&neighbor_idx]] + 1;
+                preds[scc_idx] = Some((neighbor_idx, node_idx));
+            }
+        }
+
+        SccDepths {
+            sccs,
+            scc_idxs,
+            depths,
+            preds,
+        }
+    }
+}
+
+struct SccDepths {
+    sccs: Vec<Vec<NodeIndex<u32>>>,
+    scc_idxs: HashMap<NodeIndex<u32>, usize>,
+    // The depth of each SCC.
+    depths: Vec<usize>,
+    // For each SCC that isn't a root, a link into it from a deepest preceding SCC, as
+    // (node in the preceding SCC, node in this SCC).
+    preds: Vec<Option<(NodeIndex<u32>, NodeIndex<u32>)>>,
+}
@@ -0,0 +1,99 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::diff::{
+    lockfile_package_id, package_key, ChangedLink, DiffOptions, GraphDiff, PackageKey,
+};
+use crate::graph::{DependencyEdge, PackageGraph};
+use crate::lockfile::PackageId;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// ## Diffs
+impl PackageGraph {
+    /// Diffs this graph against another one, treating this graph as the old one.
+    ///
+    /// Packages are diffed as in `DiffOptions::diff_graphs`. Dependency links are identified by the
+    /// packages at either end, so links between packages present in both graphs are reported if,
+    /// for example, they were removed with `retain_edges`. Links present in both graphs are
+    /// reported as changed if their dependency kinds or requirements differ.
+    pub fn diff(&self, other: &PackageGraph) -> GraphDiff {
+        let packages = DiffOptions.diff_graphs(self, other);
+
+        let old_links = graph_links(self);
+        let new_links = graph_links(other);
+        let mut added_links: Vec<_> = new_links
+            .keys()
+            .filter(|key| !old_links.contains_key(key))
+            .cloned()
+            .collect();
+        added_links.sort_by(link_order);
+        let mut removed_links: Vec<_> = old_links
+            .keys()
+            .filter(|key| !new_links.contains_key(key))
+            .cloned()
+            .collect();
+        removed_links.sort_by(link_order);
+        let mut changed_links: Vec<_> = old_links
+            .iter()
+            .filter_map(|(key, old_edge)| {
+                let new_edge = new_links.get(key)?;
+                if old_edge == new_edge {
+                    return None;
+                }
+                let (from, to) = key.clone();
+                Some(ChangedLink {
+                    from,
+                    to,
+                    old_kinds: edge_kinds(old_edge),
+                    new_kinds: edge_kinds(new_edge),
+                    old: (*old_edge).clone(),
+                    new: (*new_edge).clone(),
+                })
+            })
+            .collect();
+        changed_links.sort_by(|a, b| link_key(&a.from, &a.to).cmp(&link_key(&b.from, &b.to)));
+
+        GraphDiff {
+            packages,
+            added_links,
+            removed_links,
+            changed_links,
+        }
+    }
+}
+
+fn graph_links(graph: &PackageGraph) -> HashMap<(PackageId, PackageId), &DependencyEdge> {
+    graph
+        .select_all()
+        .into_iter_links(None)
+        .map(|link| {
+            (
+                (lockfile_package_id(link.from), lockfile_package_id(link.to)),
+                link.edge,
+            )
+        })
+        .collect()
+}
+
+fn edge_kinds(edge: &DependencyEdge) -> Vec<&'static str> {
+    let kinds = [
+        (edge.normal(), "normal"),
+        (edge.build(), "build"),
+        (edge.dev(), "dev"),
+    ];
+    kinds
+        .iter()
+        .filter(|(metadata, _)| metadata.is_some())
+        .map(|(_, kind)| *kind)
+        .collect()
+}
+
+fn link_order(a: &(PackageId, PackageId), b: &(PackageId, PackageId)) -> Ordering {
+    link_key(&a.0, &a.1).cmp(&link_key(&b.0, &b.1))
+}
+
+/// Returns the key links are sorted by: the name, version and source of the package at either end.
+fn link_key<'a>(from: &'a PackageId, to: &'a PackageId) -> (PackageKey<'a>, PackageKey<'a>) {
+    (package_key(from), package_key(to))
+}
@@ -0,0 +1,102 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::graph::{DependencyDirection, PackageGraph};
+use crate::Error;
+use cargo_metadata::PackageId;
+use petgraph::algo::dominators;
+use petgraph::prelude::*;
+
+/// ## Dominators
+///
+/// The methods in this section compute the *dominator tree* of a package graph, which answers
+/// questions like "if this package were removed, which other packages would disappear with it?"
+impl PackageGraph {
+    /// Computes the dominator tree of this graph in the specified direction.
+    ///
+    /// The tree is rooted at a synthetic node that links to every root package (see `root_ids`).
+    /// If direction is Forward, those are the packages that nothing else depends on, e.g.
+    /// workspace members. A package `a` *dominates* a package `b` if every path from the roots to
+    /// `b` goes through `a`. In other words, `b` is only in the graph because of `a`.
+    pub fn dominators(&self, direction: DependencyDirection) -> Dominators<'_> {
+        let dep_graph = self.dep_graph();
+        let node_count = dep_graph.node_count();
+
+        // Build a copy of the graph with all links in the specified direction, plus the synthetic
+        // root. Node indexes for packages are the same as in the original graph.
+        let mut graph: Graph<(), ()> =
+            Graph::with_capacity(node_count + 1, dep_graph.edge_count() + node_count);
+        for _ in 0..node_count {
+            graph.add_node(());
+        }
+        for edge in dep_graph.edge_references() {
+            match direction {
+                DependencyDirection::Forward => graph.add_edge(edge.source(), edge.target(), ()),
+                DependencyDirection::Reverse => graph.add_edge(edge.target(), edge.source(), ()),
+            };
+        }
+        let root = graph.add_node(());
+        for root_id in self.root_ids(direction, true) {
+            let root_idx = self.node_idx(root_id).expect("root IDs should be valid");
+            graph.add_edge(root, root_idx, ());
+        }
+
+        Dominators {
+            package_graph: self,
+            dominators: dominators::simple_fast(&graph, root),
+        }
+    }
+}
+
+/// The dominator tree of a package graph, returned by `PackageGraph::dominators`.
+pub struct Dominators<'g> {
+    package_graph: &'g PackageGraph,
+    dominators: dominators::Dominators<NodeIndex<u32>>,
+}
+
+impl<'g> Dominators<'g> {
+    /// Returns the *immediate dominator* of this package: the closest package that every path to
+    /// it goes through.
+    ///
+    /// Returns `None` if this package is a root, if it is reachable from several roots through
+    /// disjoint paths, or if it isn't reachable from any root. Returns an error if the package ID
+    /// is unknown.
+    pub fn immediate_dominator(
+        &self,
+        package_id: &PackageId,
+    ) -> Result<Option<&'g PackageId>, Error> {
+        let node_idx = self.node_idx(package_id)?;
+        Ok(self
+            .dominators
+            .immediate_dominator(node_idx)
+            .filter(|idom| *idom != self.dominators.root())
+            .map(|idom| &self.package_graph.dep_graph()[idom]))
+    }
+
+    /// Returns the packages this package strictly dominates, i.e. the packages that would no
+    /// longer be in the graph if this package were removed.
+    ///
+    /// The package itself is not included. Returns an error if the package ID is unknown.
+    pub fn dominated_by(&self, package_id: &PackageId) -> Result<Vec<&'g PackageId>, Error> {
+        let node_idx = self.node_idx(package_id)?;
+        let dep_graph = self.package_graph.dep_graph();
+        Ok(dep_graph
+            .node_indices()
+            .filter(|other_idx| {
+                *other_idx != node_idx
+                    && self
+                        .dominators
+                        .strict_dominators(*other_idx)
+                        .map(|mut iter| iter.any(|dom_idx| dom_idx == node_idx))
+                        .unwrap_or(false)
+            })
+            .map(|other_idx| &dep_graph[other_idx])
+            .collect())
+    }
+
+    fn node_idx(&self, package_id: &PackageId) -> Result<NodeIndex<u32>, Error> {
+        self.package_graph
+            .node_idx(package_id)
+            .ok_or_else(|| Error::DepGraphUnknownPackageId(package_id.clone()))
+    }
+}
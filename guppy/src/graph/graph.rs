@@ -2,15 +2,19 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::errors::Error;
-use crate::graph::{kind_str, DependencyDirection};
+use crate::graph::build::{defaulted_field_warnings, fill_missing_fields};
+use crate::graph::{kind_str, BuildWarning, DependencyDirection};
 use cargo_metadata::{Dependency, DependencyKind, Metadata, MetadataCommand, NodeDep, PackageId};
+use fixedbitset::FixedBitSet;
 use lazy_static::lazy_static;
 use petgraph::algo::{has_path_connecting, toposort, DfsSpace};
 use petgraph::prelude::*;
-use petgraph::visit::{IntoNeighborsDirected, IntoNodeIdentifiers, Visitable};
+use petgraph::visit::{EdgeFiltered, IntoNeighborsDirected, IntoNodeIdentifiers, Visitable};
 use semver::{Version, VersionReq};
 use serde_json;
-use std::collections::{BTreeMap, HashMap};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::iter;
 use std::path::{Path, PathBuf};
 
@@ -19,9 +23,14 @@ use std::path::{Path, PathBuf};
 pub struct PackageGraph {
     // Source of truth data.
     pub(super) dep_graph: Graph<PackageId, DependencyEdge>,
+    // Precomputed sets of node indexes for fast membership checks. These are derived from dep_graph
+    // and data, and must be kept in sync with them.
+    pub(super) workspace_set: FixedBitSet,
+    pub(super) direct_third_party_set: FixedBitSet,
     // XXX Should this be in an Arc for quick cloning? Not clear how this would work with node
     // filters though.
     pub(super) data: PackageGraphData,
+    pub(super) build_warnings: Vec<BuildWarning>,
 }
 
 /// Per-package data for a PackageGraph instance.
@@ -39,8 +48,49 @@ impl PackageGraph {
 
     /// Constructs a package graph from the given JSON output of `cargo metadata`.
     pub fn from_json(json: impl AsRef<str>) -> Result<Self, Error> {
-        let metadata = serde_json::from_str(json.as_ref()).map_err(Error::MetadataParseError)?;
-        Self::new(metadata)
+        Self::from_json_bytes(json.as_ref().as_bytes())
+    }
+
+    /// Constructs a package graph from the given JSON output of `cargo metadata`, as raw bytes.
+    ///
+    /// This avoids converting the output to a `String` first, which can be expensive for very large
+    /// workspaces.
+    ///
+    /// If non-critical fields are missing from packages or their dependencies, they're filled in
+    /// with defaults and recorded in `build_warnings`:
+    /// * `edition` defaults to "2015", and `authors`, `categories` and `keywords` to none.
+    /// * `features` defaults to no features, and `targets` to no targets.
+    /// * A dependency's `kind` defaults to a normal dependency, `optional` to false,
+    ///   `uses_default_features` to true and `features` to none.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn from_json_bytes(json: &[u8]) -> Result<Self, Error> {
+        let err = match serde_json::from_slice(json) {
+            Ok(metadata) => {
+                let build_warnings = defaulted_field_warnings(json, &metadata);
+                let mut graph = Self::new(metadata)?;
+                graph.build_warnings = build_warnings;
+                return Ok(graph);
+            }
+            Err(err) => err,
+        };
+
+        // Only retry if the JSON is well-formed but doesn't match the expected structure. The
+        // value is only parsed on this slow path, to avoid its cost for well-formed metadata.
+        if err.is_data() {
+            if let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(json) {
+                let build_warnings = fill_missing_fields(&mut value);
+                if !build_warnings.is_empty() {
+                    if let Ok(metadata) = serde_json::from_value(value) {
+                        let mut graph = Self::new(metadata)?;
+                        graph.build_warnings = build_warnings;
+                        return Ok(graph);
+                    }
+                }
+            }
+        }
+
+        let package_id = nearest_package_id(json, err.line(), err.column());
+        Err(Error::MetadataParseError(err, package_id))
     }
 
     /// Constructs a package graph from the given metadata.
@@ -74,8 +124,24 @@ impl PackageGraph {
             )));
         }
 
+        // Membership set checks.
+        if self.direct_third_party_set
+            != direct_third_party_set(&self.dep_graph, &self.workspace_set)
+        {
+            return Err(Error::DepGraphInternalError(
+                "direct third-party set out of sync with dep graph".into(),
+            ));
+        }
+
         for metadata in self.packages() {
             let package_id = metadata.id();
+            if metadata.in_workspace() != self.workspace_set.contains(metadata.node_idx.index()) {
+                return Err(Error::DepGraphInternalError(format!(
+                    "{}: workspace set out of sync with metadata",
+                    package_id
+                )));
+            }
+
             for dep in self.dep_links_node_idx_directed(metadata.node_idx, Outgoing) {
                 let to_id = dep.to.id();
                 let to_version = dep.to.version();
@@ -128,6 +194,12 @@ impl PackageGraph {
         Ok(())
     }
 
+    /// Returns the non-critical fields that were missing from the metadata this graph was
+    /// constructed from, and that were filled in with defaults. See `from_json_bytes`.
+    pub fn build_warnings(&self) -> &[BuildWarning] {
+        &self.build_warnings
+    }
+
     /// Returns information about the workspace.
     pub fn workspace(&self) -> &Workspace {
         &self.data.workspace()
@@ -163,6 +235,75 @@ impl PackageGraph {
         self.data.metadata(package_id)
     }
 
+    /// Returns an iterator over all the packages that use the given Rust edition, e.g. `"2018"`.
+    pub fn packages_by_edition<'g, 'a>(
+        &'g self,
+        edition: &'a str,
+    ) -> impl Iterator<Item = &'g PackageMetadata> + 'a
+    where
+        'g: 'a,
+    {
+        self.packages()
+            .filter(move |package| package.edition() == edition)
+    }
+
+    /// Returns true if the given package is a member of the workspace.
+    ///
+    /// This is a fast membership check backed by a precomputed set. Returns false if the package
+    /// ID is unknown.
+    pub fn in_workspace(&self, package_id: &PackageId) -> bool {
+        self.node_idx(package_id)
+            .map(|node_idx| self.workspace_set.contains(node_idx.index()))
+            .unwrap_or(false)
+    }
+
+    /// Returns true if the given package is a *direct third-party dependency*, i.e. a package
+    /// outside the workspace that at least one workspace member directly depends on.
+    ///
+    /// This is a fast membership check backed by a precomputed set. Returns false if the package
+    /// ID is unknown.
+    pub fn is_direct_third_party(&self, package_id: &PackageId) -> bool {
+        self.node_idx(package_id)
+            .map(|node_idx| self.direct_third_party_set.contains(node_idx.index()))
+            .unwrap_or(false)
+    }
+
+    /// Returns the packages outside the workspace that are direct dev-dependencies of workspace
+    /// members, and aren't otherwise used by the workspace.
+    ///
+    /// A package is excluded if it is reachable from any workspace member through links that
+    /// aren't dev-only, even if some other workspace member has it as a dev-dependency. The
+    /// returned packages are test and bench helpers that could be moved behind a feature.
+    pub fn workspace_dev_only_direct(&self) -> Vec<&PackageId> {
+        let workspace_idxs: Vec<_> = self.workspace_set.ones().map(NodeIndex::new).collect();
+
+        // Find everything reachable from the workspace through links that aren't dev-only.
+        let non_dev_graph =
+            EdgeFiltered::from_fn(&self.dep_graph, |edge| !edge.weight().dev_only());
+        let mut dfs = Dfs::empty(&non_dev_graph);
+        dfs.stack.extend(workspace_idxs.iter().copied());
+        while dfs.next(&non_dev_graph).is_some() {}
+        let non_dev_reachable = dfs.discovered;
+
+        let mut dev_only_set = FixedBitSet::with_capacity(self.dep_graph.node_count());
+        for workspace_idx in workspace_idxs {
+            for edge in self.dep_graph.edges_directed(workspace_idx, Outgoing) {
+                let target = edge.target().index();
+                if edge.weight().dev_only()
+                    && !self.workspace_set.contains(target)
+                    && !non_dev_reachable.contains(target)
+                {
+                    dev_only_set.insert(target);
+                }
+            }
+        }
+
+        dev_only_set
+            .ones()
+            .map(|idx| &self.dep_graph[NodeIndex::new(idx)])
+            .collect()
+    }
+
     /// Keeps all edges that return true from the visit closure, and removes the others.
     ///
     /// The order edges are visited is not specified.
@@ -182,6 +323,8 @@ impl PackageGraph {
             let edge = &frozen_graph[edge_idx];
             visit(data, DependencyLink { from, to, edge })
         });
+        // Removing edges may have changed which packages are direct third-party dependencies.
+        self.direct_third_party_set = direct_third_party_set(&self.dep_graph, &self.workspace_set);
     }
 
     /// Creates a new cache for `depends_on` queries.
@@ -231,6 +374,58 @@ impl PackageGraph {
         self.dep_links_impl(package_id, Incoming)
     }
 
+    /// Returns the number of direct dependencies of the given package, or `None` if the package ID
+    /// is unknown.
+    ///
+    /// This is the same as counting `dep_links`, but doesn't construct the links.
+    pub fn direct_dep_count(&self, package_id: &PackageId) -> Option<usize> {
+        self.node_idx(package_id)
+            .map(|node_idx| self.dep_graph.edges_directed(node_idx, Outgoing).count())
+    }
+
+    /// Returns the number of packages that directly depend on the given package, or `None` if the
+    /// package ID is unknown.
+    ///
+    /// This is the same as counting `reverse_dep_links`, but doesn't construct the links.
+    pub fn direct_dependent_count(&self, package_id: &PackageId) -> Option<usize> {
+        self.node_idx(package_id)
+            .map(|node_idx| self.dep_graph.edges_directed(node_idx, Incoming).count())
+    }
+
+    /// Returns every distinct platform-specific target that appears on a dependency link, in
+    /// sorted order.
+    ///
+    /// Targets are the strings used in `[target.'...'.dependencies]` sections of manifests, either
+    /// a `cfg()` expression or a target triple. These are the platforms a workspace's dependencies
+    /// can vary on.
+    pub fn distinct_targets(&self) -> impl Iterator<Item = &str> {
+        self.dep_graph
+            .edge_references()
+            .flat_map(|edge| {
+                let edge = edge.weight();
+                vec![edge.normal(), edge.build(), edge.dev()]
+            })
+            .flatten()
+            .filter_map(|dep_metadata| dep_metadata.target())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+    }
+
+    /// Returns the workspace members that directly depend on the given package.
+    ///
+    /// For a package outside the workspace, this is non-empty exactly when
+    /// `is_direct_third_party` returns true.
+    pub fn workspace_dependents<'g>(
+        &'g self,
+        package_id: &PackageId,
+    ) -> Option<impl Iterator<Item = &'g PackageMetadata> + 'g> {
+        self.reverse_dep_links(package_id).map(|links| {
+            links
+                .filter(|link| link.from.in_workspace())
+                .map(|link| link.from)
+        })
+    }
+
     fn dep_links_impl<'g>(
         &'g self,
         package_id: &PackageId,
@@ -250,6 +445,44 @@ impl PackageGraph {
             .map(move |edge| self.edge_to_link(edge.source(), edge.target(), edge.weight()))
     }
 
+    /// Returns the *root packages* of this graph in the specified direction.
+    ///
+    /// * If direction is Forward, return the packages that no other packages depend on.
+    /// * If direction is Reverse, return the packages that don't have any dependencies.
+    ///
+    /// If `include_dev` is false, dev-only links are ignored while determining roots.
+    pub fn root_ids<'g>(
+        &'g self,
+        direction: DependencyDirection,
+        include_dev: bool,
+    ) -> impl Iterator<Item = &'g PackageId> + 'g {
+        // Roots in the forward direction are the nodes with no incoming links.
+        let incoming = direction.opposite().to_direction();
+        self.dep_graph
+            .node_indices()
+            .filter(move |node_idx| {
+                !self
+                    .dep_graph
+                    .edges_directed(*node_idx, incoming)
+                    .any(|edge| include_dev || !edge.weight().dev_only())
+            })
+            .map(move |node_idx| &self.dep_graph[node_idx])
+    }
+
+    /// Returns the *leaf packages* of this graph in the specified direction.
+    ///
+    /// * If direction is Forward, return the packages that don't have any dependencies.
+    /// * If direction is Reverse, return the packages that no other packages depend on.
+    ///
+    /// If `include_dev` is false, dev-only links are ignored while determining leaves.
+    pub fn leaf_ids<'g>(
+        &'g self,
+        direction: DependencyDirection,
+        include_dev: bool,
+    ) -> impl Iterator<Item = &'g PackageId> + 'g {
+        self.root_ids(direction.opposite(), include_dev)
+    }
+
     // For more traversals, see select.rs.
 
     // ---
@@ -319,6 +552,55 @@ impl PackageGraph {
     }
 }
 
+/// Returns the last package ID in `json` before the given 1-based line and column, to help
+/// locate parse errors in large metadata files.
+fn nearest_package_id(json: &[u8], line: usize, column: usize) -> Option<PackageId> {
+    let line_start = if line > 1 {
+        json.iter()
+            .enumerate()
+            .filter(|(_, b)| **b == b'\n')
+            .nth(line - 2)?
+            .0
+            + 1
+    } else {
+        0
+    };
+    let end = (line_start + column).min(json.len());
+
+    let before = &json[..end];
+    let id_idx = before.windows(4).rposition(|window| window == b"\"id\"")?;
+    let value = trim_start(&before[id_idx + 4..]).strip_prefix(b":")?;
+    let value = trim_start(value).strip_prefix(b"\"")?;
+    let repr = &value[..value.iter().position(|b| *b == b'"')?];
+    Some(PackageId {
+        repr: String::from_utf8_lossy(repr).into_owned(),
+    })
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// Computes the set of packages outside the workspace that workspace members directly depend on.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub(super) fn direct_third_party_set(
+    dep_graph: &Graph<PackageId, DependencyEdge>,
+    workspace_set: &FixedBitSet,
+) -> FixedBitSet {
+    let mut set = FixedBitSet::with_capacity(dep_graph.node_count());
+    for edge in dep_graph.edge_references() {
+        let (source, target) = (edge.source().index(), edge.target().index());
+        if workspace_set.contains(source) && !workspace_set.contains(target) {
+            set.insert(target);
+        }
+    }
+    set
+}
+
 impl PackageGraphData {
     /// Returns information about the workspace.
     pub fn workspace(&self) -> &Workspace {
@@ -371,14 +653,18 @@ impl<'g> DependsCache<'g> {
         package_a: &PackageId,
         package_b: &PackageId,
     ) -> Result<bool, Error> {
-        // XXX rewrite this to avoid an allocation? meh
-        let node_idxs: Vec<_> = self
+        let a_idx = self
+            .package_graph
+            .node_idx(package_a)
+            .ok_or_else(|| Error::DepGraphUnknownPackageId(package_a.clone()))?;
+        let b_idx = self
             .package_graph
-            .node_idxs(iter::once(package_a).chain(iter::once(package_b)))?;
+            .node_idx(package_b)
+            .ok_or_else(|| Error::DepGraphUnknownPackageId(package_b.clone()))?;
         Ok(has_path_connecting(
             self.package_graph.dep_graph(),
-            node_idxs[0],
-            node_idxs[1],
+            a_idx,
+            b_idx,
             Some(&mut self.dfs_space),
         ))
     }
@@ -434,6 +720,12 @@ pub struct PackageMetadata {
     pub(super) license: Option<String>,
     pub(super) deps: Vec<Dependency>,
     pub(super) manifest_path: PathBuf,
+    pub(super) source: PackageSource,
+    pub(super) source_repr: Option<String>,
+    pub(super) edition: String,
+    pub(super) features: HashMap<String, Vec<String>>,
+    pub(super) has_build_script: bool,
+    pub(super) publish: Option<Vec<String>>,
 
     // Other information.
     pub(super) node_idx: NodeIndex<u32>,
@@ -471,15 +763,158 @@ impl PackageMetadata {
         &self.manifest_path
     }
 
+    /// Returns the dependencies declared in this package's manifest.
+    ///
+    /// Unlike the links returned by `PackageGraph::dep_links`, this includes dependencies that
+    /// were not resolved, e.g. optional dependencies that aren't enabled.
+    pub fn declared_dependencies(&self) -> &[Dependency] {
+        &self.deps
+    }
+
+    /// Returns the source this package was obtained from.
+    pub fn source(&self) -> &PackageSource {
+        &self.source
+    }
+
+    /// Returns the source of this package exactly as it appears in the metadata and in
+    /// `Cargo.lock`, e.g. `registry+https://github.com/rust-lang/crates.io-index` or
+    /// `git+https://github.com/foo/bar?branch=main#<revision>`.
+    ///
+    /// Returns `None` for path packages.
+    pub fn source_repr(&self) -> Option<&str> {
+        self.source_repr.as_deref()
+    }
+
+    /// Returns the Rust edition this package uses by default, e.g. `"2018"`.
+    ///
+    /// Individual targets within the package may override this.
+    pub fn edition(&self) -> &str {
+        &self.edition
+    }
+
+    /// Returns an iterator over the names of the features declared in this package's manifest,
+    /// in no particular order.
+    ///
+    /// This includes the `default` feature if the package declares one. It doesn't include
+    /// optional dependencies, which act as implicit features.
+    pub fn named_features(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.features.keys().map(|feature| feature.as_str())
+    }
+
+    /// Returns the number of features declared in this package's manifest.
+    ///
+    /// As with `named_features`, this includes the `default` feature if it is declared, but not
+    /// optional dependencies.
+    pub fn named_feature_count(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Returns the number of optional dependencies declared in this package's manifest.
+    ///
+    /// Each optional dependency is an implicit feature that isn't counted by
+    /// `named_feature_count`. A dependency that is optional in more than one section (e.g. both
+    /// normal and build) is counted once.
+    pub fn optional_dependency_count(&self) -> usize {
+        self.deps
+            .iter()
+            .filter(|dep| dep.optional)
+            .map(|dep| dep.rename.as_ref().unwrap_or(&dep.name))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Returns the registries this package may be published to, as specified by the `publish`
+    /// field in its manifest.
+    ///
+    /// * `None` means publishing is unrestricted.
+    /// * `Some(&[])` means publishing is forbidden (`publish = false`).
+    /// * Otherwise, the package may only be published to the listed registries.
+    ///
+    /// This is always `None` if the metadata was generated by a version of Cargo older than 1.39.
+    /// `publish_forbidden` and `is_publishable_to` are easier to get right for most checks.
+    pub fn publish(&self) -> Option<&[String]> {
+        self.publish.as_deref()
+    }
+
+    /// Returns true if this package may not be published to any registry.
+    pub fn publish_forbidden(&self) -> bool {
+        self.publish()
+            .map(|registries| registries.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// Returns true if this package may be published to the given registry.
+    ///
+    /// crates.io is called `crates-io`, matching the name Cargo uses for it in the `publish`
+    /// field.
+    pub fn is_publishable_to(&self, registry: &str) -> bool {
+        match self.publish() {
+            Some(registries) => registries.iter().any(|r| r == registry),
+            None => true,
+        }
+    }
+
+    /// Returns true if this package has a build script, i.e. a target of kind `custom-build`.
+    pub fn has_build_script(&self) -> bool {
+        self.has_build_script
+    }
+
     pub fn in_workspace(&self) -> bool {
         self.in_workspace
     }
 }
 
-#[derive(Clone, Debug)]
+// Packages are identified by their IDs, so equality is by ID. Ordering is by name and version
+// first, since that's the order in which packages are typically displayed.
+impl PartialEq for PackageMetadata {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for PackageMetadata {}
+
+impl PartialOrd for PackageMetadata {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PackageMetadata {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.name(), self.version(), self.id()).cmp(&(other.name(), other.version(), other.id()))
+    }
+}
+
+/// The source of a package: crates.io, another registry, a git repository or a local path.
+///
+/// crates.io packages are recognized whether they come from the git index or the sparse index.
+/// This is parsed once while the package graph is constructed.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum PackageSource {
+    /// The package is from crates.io.
+    CratesIo,
+    /// The package is from a registry other than crates.io. Contains the URL of the registry.
+    Registry(String),
+    /// The package is from a git repository.
+    Git {
+        /// The URL of the repository, including any query parameters like `?branch=`.
+        url: String,
+        /// The resolved revision.
+        rev: String,
+    },
+    /// The package is from a local path. Contains the directory the package is in.
+    Path(PathBuf),
+    /// The package is from a kind of source that isn't recognized. Contains the source as it
+    /// appears in the metadata.
+    Other(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct DependencyEdge {
     pub(super) dep_name: String,
     pub(super) resolved_name: String,
+    pub(super) package_name: String,
     pub(super) normal: Option<DependencyMetadata>,
     pub(super) build: Option<DependencyMetadata>,
     pub(super) dev: Option<DependencyMetadata>,
@@ -497,6 +932,12 @@ impl DependencyEdge {
         &self.resolved_name
     }
 
+    /// Returns the name of the package this edge points to, as published in its registry. This is
+    /// not affected by crate renames.
+    pub fn package_name(&self) -> &str {
+        &self.package_name
+    }
+
     pub fn normal(&self) -> Option<&DependencyMetadata> {
         self.normal.as_ref()
     }
@@ -515,9 +956,31 @@ impl DependencyEdge {
     pub fn dev_only(&self) -> bool {
         self.normal().is_none() && self.build.is_none()
     }
+
+    /// Return true if this edge is build-only, i.e. this dependency is only listed in
+    /// `[build-dependencies]`.
+    ///
+    /// Code from this edge is run at build time but isn't linked into the final artifact. To get
+    /// the runtime dependency graph, remove edges that are either build-only or dev-only with
+    /// `PackageGraph::retain_edges`.
+    pub fn build_only(&self) -> bool {
+        self.normal().is_none() && self.dev().is_none()
+    }
+
+    /// Returns true if this edge is only pulled in optionally, i.e. every section this dependency
+    /// is listed in (normal, build or dev) marks it as optional.
+    ///
+    /// An optional dependency is only used if a feature enables it. If it is optional in one
+    /// section but mandatory in another, the edge is always present and this returns false.
+    pub fn is_optional(&self) -> bool {
+        [self.normal(), self.build(), self.dev()]
+            .iter()
+            .flatten()
+            .all(|dep_metadata| dep_metadata.optional())
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct DependencyMetadata {
     // Normal/dev/build can have different version requirements even if they resolve to the same
     // version.
@@ -548,4 +1011,57 @@ impl DependencyMetadata {
     pub fn target(&self) -> Option<&str> {
         self.target.as_ref().map(|x| x.as_str())
     }
+
+    /// Returns all of the configuration requested for this dependency together, e.g. to render an
+    /// equivalent dependency specification.
+    pub fn as_request(&self) -> DependencyRequest<'_> {
+        DependencyRequest {
+            req: &self.req,
+            optional: self.optional,
+            uses_default_features: self.uses_default_features,
+            features: &self.features,
+            target: self.target(),
+        }
+    }
+}
+
+/// The full configuration requested for a dependency in a manifest, returned by
+/// `DependencyMetadata::as_request`.
+///
+/// The `Display` impl renders this as an inline table in `Cargo.toml` syntax, e.g.
+/// `{ version = "^1.0", default-features = false }`. The target isn't included, since it's
+/// specified in the section header (`[target.'cfg(unix)'.dependencies]`) instead.
+#[derive(Clone, Copy, Debug)]
+pub struct DependencyRequest<'a> {
+    /// The version requirement.
+    pub req: &'a VersionReq,
+    /// Whether the dependency is optional.
+    pub optional: bool,
+    /// Whether the dependency's default features are enabled.
+    pub uses_default_features: bool,
+    /// The features explicitly enabled on the dependency.
+    pub features: &'a [String],
+    /// The platform the dependency is restricted to, if any.
+    pub target: Option<&'a str>,
+}
+
+impl<'a> fmt::Display for DependencyRequest<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{ version = \"{}\"", self.req)?;
+        if !self.uses_default_features {
+            write!(f, ", default-features = false")?;
+        }
+        if !self.features.is_empty() {
+            let features: Vec<_> = self
+                .features
+                .iter()
+                .map(|feature| format!("\"{}\"", feature))
+                .collect();
+            write!(f, ", features = [{}]", features.join(", "))?;
+        }
+        if self.optional {
+            write!(f, ", optional = true")?;
+        }
+        write!(f, " }}")
+    }
 }
@@ -0,0 +1,80 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::errors::Error;
+use crate::graph::{DependencyEdge, PackageGraph};
+use cargo_metadata::PackageId;
+use fixedbitset::FixedBitSet;
+use petgraph::prelude::*;
+use petgraph::visit::EdgeFiltered;
+
+/// ## Host and target packages
+///
+/// Cargo builds build scripts and their dependencies for the *host* platform, and everything else
+/// for the *target* platform. The methods in this section approximate that split by following
+/// link kinds alone, without resolving features or platform-specific dependencies.
+impl PackageGraph {
+    /// Splits the packages reachable from the given roots into target and host packages.
+    ///
+    /// Dev-only links are ignored. Target packages are the roots, plus anything reachable from
+    /// them through normal dependencies. Host packages are the remaining packages reachable once
+    /// build dependencies are followed as well, i.e. the ones that are only pulled in through a
+    /// build dependency somewhere along the way.
+    ///
+    /// A package that Cargo would build for both platforms is only listed as a target package.
+    ///
+    /// Returns an error if any of the package IDs are unknown.
+    pub fn host_target_split<'g, 'a>(
+        &'g self,
+        package_ids: impl IntoIterator<Item = &'a PackageId>,
+    ) -> Result<HostTargetSplit<'g>, Error> {
+        let root_idxs: Vec<NodeIndex<u32>> = self.node_idxs(package_ids)?;
+
+        let target_set = self.reachable_through(&root_idxs, |edge| edge.normal().is_some());
+        let reachable_set = self.reachable_through(&root_idxs, |edge| !edge.dev_only());
+        let host_set = reachable_set.difference(&target_set).collect();
+
+        Ok(HostTargetSplit {
+            target: self.sorted_ids(&target_set),
+            host: self.sorted_ids(&host_set),
+        })
+    }
+
+    fn reachable_through(
+        &self,
+        root_idxs: &[NodeIndex<u32>],
+        follow: impl Fn(&DependencyEdge) -> bool,
+    ) -> FixedBitSet {
+        let filtered = EdgeFiltered::from_fn(self.dep_graph(), |edge| follow(edge.weight()));
+        let mut dfs = Dfs::empty(&filtered);
+        // Dfs marks nodes as discovered when they're pushed onto the stack, so do the same here.
+        for root_idx in root_idxs {
+            dfs.discovered.insert(root_idx.index());
+            dfs.stack.push(*root_idx);
+        }
+        while dfs.next(&filtered).is_some() {}
+        dfs.discovered
+    }
+
+    fn sorted_ids(&self, set: &FixedBitSet) -> Vec<&PackageId> {
+        let mut packages: Vec<_> = set
+            .ones()
+            .map(|idx| {
+                self.metadata(&self.dep_graph()[NodeIndex::new(idx)])
+                    .expect("valid node index")
+            })
+            .collect();
+        packages.sort();
+        packages.into_iter().map(|package| package.id()).collect()
+    }
+}
+
+/// Packages split by the platform they're built for, returned by
+/// `PackageGraph::host_target_split`.
+#[derive(Clone, Debug)]
+pub struct HostTargetSplit<'g> {
+    /// Packages built for the target platform, sorted by name and version.
+    pub target: Vec<&'g PackageId>,
+    /// Packages only built for the host platform, sorted by name and version.
+    pub host: Vec<&'g PackageId>,
+}
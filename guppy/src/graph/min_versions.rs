@@ -0,0 +1,102 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::graph::PackageGraph;
+use cargo_metadata::PackageId;
+use semver::{Version, VersionReq};
+
+/// ## Minimal versions
+///
+/// The methods in this section help find version requirements that are looser than they need to
+/// be, which is what building with `-Z minimal-versions` checks for.
+impl PackageGraph {
+    /// Returns the dependency edges where the resolved version is higher than the minimum version
+    /// allowed by the requirement.
+    ///
+    /// Each entry is `(from, to, req, min_version)`, where `min_version` is the lowest version
+    /// that `req` allows. The resolved version is the version of `to`. If normal, build and dev
+    /// dependencies on the same package have different requirements, each requirement is checked
+    /// separately.
+    ///
+    /// This is a static analysis over the requirements and versions in this graph, not a
+    /// re-resolution: it doesn't check whether `min_version` exists or would build.
+    pub fn minimal_version_violations(&self) -> Vec<(PackageId, PackageId, VersionReq, Version)> {
+        let mut violations = vec![];
+
+        for link in self.select_all().into_iter_links(None) {
+            let mut reqs: Vec<&VersionReq> = vec![];
+            let dep_metadatas = [link.edge.normal(), link.edge.build(), link.edge.dev()];
+            for dep_metadata in dep_metadatas.iter().flatten() {
+                let req = dep_metadata.req();
+                if !reqs.contains(&req) {
+                    reqs.push(req);
+                }
+            }
+
+            for req in reqs {
+                if let Some(min_version) = min_version(req) {
+                    if link.to.version() > &min_version {
+                        violations.push((
+                            link.from.id().clone(),
+                            link.to.id().clone(),
+                            req.clone(),
+                            min_version,
+                        ));
+                    }
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+/// Returns the lowest version that matches this requirement, or None if it couldn't be
+/// determined.
+///
+/// semver doesn't expose the predicates in a requirement, so the candidates are derived from its
+/// string representation: every bound in it, plus the version just above it (for `>` bounds).
+fn min_version(req: &VersionReq) -> Option<Version> {
+    let req_str = req.to_string();
+    let mut candidates = vec![Version::new(0, 0, 0)];
+    for predicate in req_str.split(',') {
+        if let Some(bound) = predicate_bound(predicate) {
+            let mut next = bound.clone();
+            next.increment_patch();
+            candidates.push(bound);
+            candidates.push(next);
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|candidate| req.matches(candidate))
+        .min()
+}
+
+/// Parses the version in a single predicate like `>= 1.2` or `0.3.*`, filling in missing or
+/// wildcard components with 0.
+fn predicate_bound(predicate: &str) -> Option<Version> {
+    let version_str =
+        predicate.trim_start_matches(|c: char| c.is_whitespace() || "=<>~^".contains(c));
+    let (numbers, pre) = match version_str.find('-') {
+        Some(idx) => (&version_str[..idx], Some(&version_str[idx + 1..])),
+        None => (version_str, None),
+    };
+
+    let mut components = numbers.trim().split('.');
+    let mut next_component = || -> Option<u64> {
+        match components.next() {
+            None | Some("*") => Some(0),
+            Some(component) => component.parse().ok(),
+        }
+    };
+    let (major, minor, patch) = (next_component()?, next_component()?, next_component()?);
+
+    let mut bound = format!("{}.{}.{}", major, minor, patch);
+    if let Some(pre) = pre {
+        bound.push('-');
+        bound.push_str(pre.trim());
+    }
+    Version::parse(&bound).ok()
+}
@@ -5,15 +5,30 @@ use cargo_metadata::DependencyKind;
 use petgraph::prelude::*;
 
 mod build;
+mod depth;
+mod diff;
+mod dominators;
 mod graph;
+mod host_target;
+mod min_versions;
+mod package_id;
 mod print;
+mod reachability;
+mod scc;
 mod select;
+mod spec;
 
 // Public exports for dot graphs.
 pub use crate::petgraph_support::dot::DotWrite;
+pub use build::BuildWarning;
+pub use dominators::Dominators;
 pub use graph::*;
+pub use host_target::HostTargetSplit;
+pub use package_id::ParsedPackageId;
 pub use print::PackageDotVisitor;
-pub use select::{DependencyLinkIter, PackageIdIter, PackageSelect};
+pub use reachability::ReachabilityMatrix;
+pub use scc::SccGroups;
+pub use select::{DependencyLinkIter, PackageIdIter, PackageSelect, SortKey};
 
 /// The direction in which to follow dependencies.
 ///
@@ -35,7 +50,7 @@ impl DependencyDirection {
         }
     }
 
-    fn to_direction(self) -> Direction {
+    pub(super) fn to_direction(self) -> Direction {
         match self {
             DependencyDirection::Forward => Direction::Outgoing,
             DependencyDirection::Reverse => Direction::Incoming,
@@ -43,7 +58,8 @@ impl DependencyDirection {
     }
 }
 
-fn kind_str(kind: DependencyKind) -> &'static str {
+/// Returns a short name for a dependency kind: `normal`, `build` or `dev`.
+pub fn kind_str(kind: DependencyKind) -> &'static str {
     match kind {
         DependencyKind::Normal => "normal",
         DependencyKind::Build => "build",
@@ -0,0 +1,96 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::errors::Error;
+use cargo_metadata::PackageId;
+use semver::Version;
+
+/// A package ID that has been checked to be well-formed, and split up into its components.
+///
+/// Package IDs produced by `cargo metadata` have the form `name version (source)`, for example
+/// `serde 1.0.99 (registry+https://github.com/rust-lang/crates.io-index)`. Package IDs are
+/// otherwise opaque, so this is mostly useful for validating IDs provided by users before looking
+/// them up in a `PackageGraph`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParsedPackageId {
+    package_id: PackageId,
+    name: String,
+    version: Version,
+    source: String,
+}
+
+impl ParsedPackageId {
+    /// Parses a package ID of the form `name version (source)`.
+    ///
+    /// Returns an error if the ID isn't of that form, if the version isn't a valid semver version,
+    /// or if the source isn't of the form `kind+url`.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let err = |msg: &str| {
+            Error::PackageIdParseError(
+                PackageId {
+                    repr: s.to_string(),
+                },
+                msg.to_string(),
+            )
+        };
+
+        let open_idx = s
+            .find(" (")
+            .ok_or_else(|| err("expected 'name version (source)'"))?;
+        let (name_version, source) = (&s[..open_idx], &s[open_idx + 2..]);
+        if !source.ends_with(')') {
+            return Err(err("source must be enclosed in parentheses"));
+        }
+        let source = &source[..source.len() - 1];
+        match source.find('+') {
+            Some(plus_idx) if plus_idx > 0 && plus_idx + 1 < source.len() => {}
+            _ => return Err(err("source must be of the form 'kind+url'")),
+        }
+
+        let mut parts = name_version.split(' ');
+        let (name, version) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(name), Some(version), None) => (name, version),
+            _ => return Err(err("expected a name and version separated by a space")),
+        };
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(err("invalid package name"));
+        }
+        let version = Version::parse(version).map_err(|e| err(&e.to_string()))?;
+
+        Ok(Self {
+            package_id: PackageId {
+                repr: s.to_string(),
+            },
+            name: name.to_string(),
+            version,
+            source: source.to_string(),
+        })
+    }
+
+    /// Returns the package ID this was parsed from.
+    pub fn package_id(&self) -> &PackageId {
+        &self.package_id
+    }
+
+    /// Converts this into the package ID it was parsed from.
+    pub fn into_package_id(self) -> PackageId {
+        self.package_id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Returns the source of this package, e.g. `registry+https://...` or `path+file://...`.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
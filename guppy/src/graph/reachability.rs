@@ -0,0 +1,97 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::errors::Error;
+use crate::graph::PackageGraph;
+use cargo_metadata::PackageId;
+use fixedbitset::FixedBitSet;
+use petgraph::algo::tarjan_scc;
+use petgraph::prelude::*;
+
+/// ## Reachability
+///
+/// `depends_on` and `DependsCache` search the graph afresh for every query. For tools that issue
+/// a very large number of queries, e.g. one for every pair of packages, it can be faster to
+/// precompute the transitive closure of the graph once.
+impl PackageGraph {
+    /// Computes the transitive closure of this graph, which can then answer `depends_on` queries
+    /// in constant time.
+    ///
+    /// The matrix stores one bit per package for every strongly connected component of the graph.
+    /// Since graphs are mostly acyclic, this is close to `n * n / 8` bytes for `n` packages, e.g.
+    /// around 125KB for a graph with 1000 packages.
+    pub fn reachability_matrix(&self) -> ReachabilityMatrix<'_> {
+        let dep_graph = self.dep_graph();
+        let node_count = dep_graph.node_count();
+
+        // tarjan_scc returns SCCs in reverse topological order, so every SCC is visited after all
+        // the SCCs it has links to.
+        let sccs = tarjan_scc(dep_graph);
+        let mut scc_idxs = vec![0; node_count];
+        for (scc_idx, scc) in sccs.iter().enumerate() {
+            for node_idx in scc {
+                scc_idxs[node_idx.index()] = scc_idx;
+            }
+        }
+
+        let mut reachable: Vec<FixedBitSet> = Vec::with_capacity(sccs.len());
+        for (scc_idx, scc) in sccs.iter().enumerate() {
+            let mut set = FixedBitSet::with_capacity(node_count);
+            for node_idx in scc {
+                set.insert(node_idx.index());
+                for neighbor in dep_graph.neighbors_directed(*node_idx, Outgoing) {
+                    let neighbor_scc_idx = scc_idxs[neighbor.index()];
+                    if neighbor_scc_idx != scc_idx {
+                        union_with(&mut set, &reachable[neighbor_scc_idx]);
+                    }
+                }
+            }
+            reachable.push(set);
+        }
+
+        ReachabilityMatrix {
+            package_graph: self,
+            scc_idxs,
+            reachable,
+        }
+    }
+}
+
+/// The precomputed transitive closure of a package graph, returned by
+/// `PackageGraph::reachability_matrix`.
+///
+/// This holds a shared reference to the package graph, so the matrix can't outlive changes to the
+/// graph.
+#[derive(Clone, Debug)]
+pub struct ReachabilityMatrix<'g> {
+    package_graph: &'g PackageGraph,
+    // The index of the strongly connected component each node belongs to.
+    scc_idxs: Vec<usize>,
+    // For every strongly connected component, the set of nodes reachable from it (including its
+    // own nodes).
+    reachable: Vec<FixedBitSet>,
+}
+
+impl<'g> ReachabilityMatrix<'g> {
+    /// Returns true if `package_a` depends (directly or indirectly) on `package_b`.
+    ///
+    /// This returns the same results as `PackageGraph::depends_on`.
+    pub fn depends_on(&self, package_a: &PackageId, package_b: &PackageId) -> Result<bool, Error> {
+        let a_idx = self
+            .package_graph
+            .node_idx(package_a)
+            .ok_or_else(|| Error::DepGraphUnknownPackageId(package_a.clone()))?;
+        let b_idx = self
+            .package_graph
+            .node_idx(package_b)
+            .ok_or_else(|| Error::DepGraphUnknownPackageId(package_b.clone()))?;
+        Ok(self.reachable[self.scc_idxs[a_idx.index()]].contains(b_idx.index()))
+    }
+}
+
+/// Adds all the bits in `other` to `set`. Both sets must have the same capacity.
+fn union_with(set: &mut FixedBitSet, other: &FixedBitSet) {
+    for (block, other_block) in set.as_mut_slice().iter_mut().zip(other.as_slice()) {
+        *block |= *other_block;
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::graph::PackageGraph;
+use cargo_metadata::PackageId;
+use petgraph::algo::tarjan_scc;
+use petgraph::prelude::*;
+use petgraph::visit::{EdgeFiltered, IntoNeighbors};
+use std::collections::{BTreeSet, HashMap};
+
+/// ## Strongly connected components
+///
+/// Package graphs are usually acyclic, but dev-dependencies can introduce cycles: for example, a
+/// crate may have a dev-dependency on a test helper that itself depends on the crate.
+impl PackageGraph {
+    /// Returns the *condensation* of this graph: every strongly connected component (SCC) as a
+    /// group of packages, plus the links between groups.
+    ///
+    /// Packages that aren't part of a cycle form groups of their own.
+    pub fn scc_groups(&self) -> SccGroups<'_> {
+        let dep_graph = self.dep_graph();
+
+        // tarjan_scc returns SCCs in reverse topological order.
+        let mut sccs = tarjan_scc(dep_graph);
+        sccs.reverse();
+
+        let mut group_idxs = HashMap::with_capacity(dep_graph.node_count());
+        for (group_idx, scc) in sccs.iter().enumerate() {
+            group_idxs.extend(scc.iter().map(|node_idx| (*node_idx, group_idx)));
+        }
+
+        let links: BTreeSet<_> = dep_graph
+            .edge_references()
+            .map(|edge| (group_idxs[&edge.source()], group_idxs[&edge.target()]))
+            .filter(|(from, to)| from != to)
+            .collect();
+
+        SccGroups {
+            groups: sccs
+                .into_iter()
+                .map(|scc| {
+                    scc.into_iter()
+                        .map(|node_idx| &dep_graph[node_idx])
+                        .collect()
+                })
+                .collect(),
+            links: links.into_iter().collect(),
+        }
+    }
+
+    /// Checks that this graph has no cycles once dev-only links are ignored.
+    ///
+    /// Cargo rejects cycles through normal and build dependencies, so metadata it produced should
+    /// always pass this check. On failure, returns every cycle as the list of packages in it,
+    /// sorted by package ID.
+    pub fn check_acyclic_non_dev(&self) -> Result<(), Vec<Vec<PackageId>>> {
+        let filtered = EdgeFiltered::from_fn(self.dep_graph(), |edge| !edge.weight().dev_only());
+
+        let mut cycles: Vec<Vec<PackageId>> = tarjan_scc(&filtered)
+            .into_iter()
+            .filter(|scc| {
+                scc.len() > 1
+                    || filtered
+                        .neighbors(scc[0])
+                        .any(|neighbor| neighbor == scc[0])
+            })
+            .map(|scc| {
+                let mut cycle: Vec<_> = scc
+                    .into_iter()
+                    .map(|node_idx| self.dep_graph()[node_idx].clone())
+                    .collect();
+                cycle.sort();
+                cycle
+            })
+            .collect();
+
+        if cycles.is_empty() {
+            Ok(())
+        } else {
+            cycles.sort();
+            Err(cycles)
+        }
+    }
+}
+
+/// The strongly connected components of a package graph, returned by `PackageGraph::scc_groups`.
+#[derive(Clone, Debug)]
+pub struct SccGroups<'g> {
+    /// The packages in each group, in topological order: a group is always listed before the
+    /// groups it depends on.
+    pub groups: Vec<Vec<&'g PackageId>>,
+    /// The links between groups, as `(from, to)` indexes into `groups`. Each pair is listed once,
+    /// in sorted order.
+    pub links: Vec<(usize, usize)>,
+}
+
+impl<'g> SccGroups<'g> {
+    /// Returns the groups with more than one package, i.e. the dependency cycles in the graph.
+    pub fn cycles(&self) -> impl Iterator<Item = &[&'g PackageId]> + '_ {
+        self.groups
+            .iter()
+            .filter(|group| group.len() > 1)
+            .map(|group| group.as_slice())
+    }
+}
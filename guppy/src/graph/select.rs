@@ -80,17 +80,27 @@ impl PackageGraph {
 impl<'g> PackageSelect<'g> {
     /// Returns the set of "root packages" in the specified direction.
     ///
-    /// * If direction is Forward, return the set of packages that do not have any dependencies
+    /// * If direction is Forward, return the set of packages that do not have any dependents
+    ///   within the selected graph, i.e. its entry points.
+    /// * If direction is Reverse, return the set of packages that do not have any dependencies
     ///   within the selected graph.
-    /// * If direction is Reverse, return the set of packages that do not have any dependents within
-    ///   the selected graph.
+    pub fn root_ids(
+        &self,
+        direction: DependencyDirection,
+    ) -> impl Iterator<Item = &'g PackageId> + 'g {
+        let dep_graph = self.package_graph.dep_graph();
+        let (_, roots) = select_postfilter(dep_graph, &self.params, direction);
+        roots.into_iter().map(move |node_idx| &dep_graph[node_idx])
+    }
+
+    /// Consumes this query and returns the set of "root packages" in the specified direction.
+    ///
+    /// This is the same as `root_ids`.
     pub fn into_root_ids(
         self,
         direction: DependencyDirection,
     ) -> impl IntoIterator<Item = &'g PackageId> + 'g {
-        let dep_graph = self.package_graph.dep_graph();
-        let (_, roots) = select_postfilter(dep_graph, self.params, direction);
-        roots.into_iter().map(move |node_idx| &dep_graph[node_idx])
+        self.root_ids(direction)
     }
 
     /// Consumes this query and creates an iterator over package IDs, returned in topological order.
@@ -121,6 +131,35 @@ impl<'g> PackageSelect<'g> {
         }
     }
 
+    /// Consumes this query and returns the selected package IDs, sorted by the given key.
+    ///
+    /// For `SortKey::Topo`, package IDs are returned in the default order of iteration (see
+    /// `into_iter_ids`). Ties in other sort keys are broken by name, version and then package ID,
+    /// so the returned order is always deterministic.
+    pub fn into_ids_sorted_by(self, sort_key: SortKey) -> Vec<&'g PackageId> {
+        let package_graph = self.package_graph;
+        let ids = self.into_iter_ids(None);
+        if sort_key == SortKey::Topo {
+            return ids.collect();
+        }
+
+        let mut packages: Vec<_> = ids
+            .map(|package_id| {
+                package_graph
+                    .metadata(package_id)
+                    .expect("selected package should have associated metadata")
+            })
+            .collect();
+        match sort_key {
+            SortKey::Name => packages.sort(),
+            SortKey::Version => packages.sort_by(|a, b| {
+                (a.version(), a.name(), a.id()).cmp(&(b.version(), b.name(), b.id()))
+            }),
+            SortKey::Topo => unreachable!("topo order was handled above"),
+        }
+        packages.into_iter().map(|package| package.id()).collect()
+    }
+
     /// Consumes this query and creates an iterator over dependency links.
     ///
     /// If the iteration is in forward order, for any given package, at least one link where the
@@ -143,7 +182,7 @@ impl<'g> PackageSelect<'g> {
         let direction = direction_opt.unwrap_or_else(|| self.params.default_direction());
         let dep_graph = self.package_graph.dep_graph();
 
-        let (reachable, roots) = select_postfilter(dep_graph, self.params, direction);
+        let (reachable, roots) = select_postfilter(dep_graph, &self.params, direction);
 
         let (reachable, edge_dfs) = match (reachable, direction) {
             (Some(reachable), Forward) => {
@@ -169,6 +208,17 @@ impl<'g> PackageSelect<'g> {
     }
 }
 
+/// The key to sort selected packages by. Used by `PackageSelect::into_ids_sorted_by`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SortKey {
+    /// Sort packages by name.
+    Name,
+    /// Sort packages by version.
+    Version,
+    /// Return packages in topological order.
+    Topo,
+}
+
 /// Computes intermediate state for operations where the graph must be pre-filtered before any
 /// traversals happen.
 pub(super) fn select_prefilter(
@@ -188,7 +238,7 @@ pub(super) fn select_prefilter(
 /// possible.
 fn select_postfilter(
     graph: &Graph<PackageId, DependencyEdge>,
-    params: PackageSelectParams,
+    params: &PackageSelectParams,
     direction: DependencyDirection,
 ) -> (Option<FixedBitSet>, Vec<NodeIndex<u32>>) {
     use DependencyDirection::*;
@@ -208,11 +258,11 @@ fn select_postfilter(
         }
         (TransitiveDeps(roots), Forward) => {
             // No need for a reachable map.
-            (None, roots)
+            (None, roots.clone())
         }
         (TransitiveDeps(roots), Reverse) => {
             // Forward traversal + reverse order = need to compute reachable map.
-            let (reachable, _) = reachable_map(graph, roots);
+            let (reachable, _) = reachable_map(graph, roots.clone());
             let filtered_reversed_graph = NodeFiltered(ReversedDirected(graph), reachable);
             // The filtered + reversed graph will have its own roots since the iteration order
             // is reversed from the specified roots.
@@ -223,7 +273,7 @@ fn select_postfilter(
         (TransitiveReverseDeps(roots), Forward) => {
             // Reverse traversal + forward order = need to compute reachable map.
             let reversed_graph = ReversedDirected(graph);
-            let (reachable, _) = reachable_map(reversed_graph, roots);
+            let (reachable, _) = reachable_map(reversed_graph, roots.clone());
             let filtered_graph = NodeFiltered(graph, reachable);
             // The filtered graph will have its own roots since the iteration order is reversed
             // from the specified roots.
@@ -233,7 +283,7 @@ fn select_postfilter(
         }
         (TransitiveReverseDeps(roots), Reverse) => {
             // No need for a reachable map.
-            (None, roots)
+            (None, roots.clone())
         }
     }
 }
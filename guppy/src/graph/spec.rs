@@ -0,0 +1,162 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::errors::Error;
+use crate::graph::{PackageGraph, PackageMetadata, PackageSource};
+use cargo_metadata::PackageId;
+use semver::Version;
+use std::path::Path;
+
+/// ## Package ID specifications
+///
+/// The methods in this section look up packages using the same
+/// [specification syntax](https://doc.rust-lang.org/cargo/reference/pkgid-spec.html) as Cargo's
+/// `-p` argument.
+impl PackageGraph {
+    /// Resolves a package ID specification to the single package it refers to.
+    ///
+    /// The specification can be any of:
+    /// * `name`, e.g. `serde`
+    /// * `name:version` or `name@version`, e.g. `serde:1.0.99`
+    /// * `url`, `url#version` or `url#name:version`, where the URL is the source of the package,
+    ///   e.g. `https://github.com/rust-lang/crates.io-index#serde:1.0.99`. If the name is omitted,
+    ///   it's taken to be the last path segment of the URL. The URL may be prefixed with the kind
+    ///   of source, as in the package IDs printed by newer versions of Cargo, e.g.
+    ///   `registry+https://github.com/rust-lang/crates.io-index#serde@1.0.99`.
+    ///
+    /// URLs are matched against the parsed source of each package, so this works regardless of
+    /// the format of package IDs.
+    ///
+    /// Returns an error if the specification matches no packages, or if it matches more than one.
+    /// In the latter case, the error lists the matching package IDs.
+    pub fn resolve_spec(&self, spec: &str) -> Result<&PackageId, Error> {
+        let parsed = PackageIdSpec::parse(spec)?;
+        let mut matches: Vec<_> = self
+            .packages()
+            .filter(|package| parsed.matches(package))
+            .collect();
+        matches.sort();
+
+        match matches.as_slice() {
+            [] => Err(Error::DepGraphError(format!(
+                "package ID specification '{}' did not match any packages",
+                spec
+            ))),
+            [package] => Ok(package.id()),
+            _ => {
+                let candidates: Vec<_> = matches
+                    .iter()
+                    .map(|package| package.id().to_string())
+                    .collect();
+                Err(Error::DepGraphError(format!(
+                    "package ID specification '{}' is ambiguous, matching:\n  {}",
+                    spec,
+                    candidates.join("\n  ")
+                )))
+            }
+        }
+    }
+}
+
+struct PackageIdSpec<'a> {
+    name: &'a str,
+    version: Option<Version>,
+    url: Option<&'a str>,
+}
+
+impl<'a> PackageIdSpec<'a> {
+    fn parse(spec: &'a str) -> Result<Self, Error> {
+        let invalid = |msg: &str| {
+            Error::DepGraphError(format!(
+                "invalid package ID specification '{}': {}",
+                spec, msg
+            ))
+        };
+        let parse_version =
+            |version: &str| Version::parse(version).map_err(|err| invalid(&err.to_string()));
+
+        let (url, name_version) = if spec.contains("://") {
+            match spec.find('#') {
+                Some(hash_idx) => (Some(&spec[..hash_idx]), Some(&spec[hash_idx + 1..])),
+                None => (Some(spec), None),
+            }
+        } else {
+            (None, Some(spec))
+        };
+        let url_name = || {
+            url.and_then(|url| strip_url(url).rsplit('/').next())
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| invalid("no package name in URL"))
+        };
+
+        let (name, version) = match name_version {
+            Some(name_version) => match name_version.find(&[':', '@'][..]) {
+                Some(colon_idx) => (
+                    &name_version[..colon_idx],
+                    Some(parse_version(&name_version[colon_idx + 1..])?),
+                ),
+                // A URL fragment without a separator can either be a version or a name.
+                None if url.is_some() => match Version::parse(name_version) {
+                    Ok(version) => (url_name()?, Some(version)),
+                    Err(_) => (name_version, None),
+                },
+                None => (name_version, None),
+            },
+            None => (url_name()?, None),
+        };
+        if name.is_empty() {
+            return Err(invalid("empty package name"));
+        }
+
+        Ok(Self { name, version, url })
+    }
+
+    // Option::is_some_and requires a newer Rust than this crate supports.
+    #[allow(clippy::unnecessary_map_or)]
+    fn matches(&self, package: &PackageMetadata) -> bool {
+        if package.name() != self.name {
+            return false;
+        }
+        if let Some(version) = &self.version {
+            if package.version() != version {
+                return false;
+            }
+        }
+        let url = match self.url {
+            Some(url) => strip_url(url),
+            None => return true,
+        };
+        match package.source() {
+            PackageSource::CratesIo => {
+                url == strip_url(CRATES_IO_GIT_URL) || url == strip_url(CRATES_IO_SPARSE_URL)
+            }
+            PackageSource::Registry(source_url)
+            | PackageSource::Git {
+                url: source_url, ..
+            } => strip_url(source_url) == url,
+            PackageSource::Path(path) => url
+                .strip_prefix("file://")
+                .map_or(false, |url_path| Path::new(url_path) == path),
+            // The source is of the form "kind+url".
+            PackageSource::Other(source) => source
+                .find('+')
+                .map_or(false, |plus_idx| strip_url(&source[plus_idx + 1..]) == url),
+        }
+    }
+}
+
+const CRATES_IO_GIT_URL: &str = "https://github.com/rust-lang/crates.io-index";
+const CRATES_IO_SPARSE_URL: &str = "https://index.crates.io/";
+
+/// Removes any source kind (e.g. `registry+`), query, fragment and trailing slash from a URL.
+fn strip_url(url: &str) -> &str {
+    let url = match url.find("://") {
+        Some(scheme_idx) => match url[..scheme_idx].find('+') {
+            Some(plus_idx) => &url[plus_idx + 1..],
+            None => url,
+        },
+        None => url,
+    };
+    let end = url.find(&['?', '#'][..]).unwrap_or(url.len());
+    url[..end].trim_end_matches('/')
+}
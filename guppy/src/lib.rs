@@ -121,5 +121,7 @@ pub mod lockfile;
 pub(crate) mod petgraph_support;
 #[cfg(test)]
 mod unit_tests;
+#[cfg(feature = "yanked")]
+pub mod yanked;
 
 pub use errors::Error;
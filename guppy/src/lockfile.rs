@@ -6,7 +6,12 @@ use serde::{
     de::{Error as _, IntoDeserializer},
     Deserialize, Deserializer, Serialize, Serializer,
 };
-use std::{collections::HashMap, fs::File, io::Read, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::Read,
+    str::FromStr,
+};
 use toml;
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -44,20 +49,23 @@ impl Lockfile {
             .count()
     }
 
-    pub fn duplicate_packages(&self) {
-        let mut map = HashMap::new();
+    /// Returns the packages that are present at more than one version in this lockfile, keyed and
+    /// sorted by name.
+    pub fn duplicates(&self) -> BTreeMap<&str, Vec<PackageId>> {
+        let mut map = BTreeMap::new();
 
         for pkg in self.packages() {
             map.entry(pkg.name())
-                .or_insert(Vec::new())
+                .or_insert_with(Vec::new)
                 .push(pkg.package_id());
         }
 
-        for (name, duplicates) in map {
-            if duplicates.len() <= 1 {
-                continue;
-            }
+        map.retain(|_, duplicates| duplicates.len() > 1);
+        map
+    }
 
+    pub fn duplicate_packages(&self) {
+        for (name, duplicates) in self.duplicates() {
             print!("{} ({}", name, duplicates[0].version());
 
             for pkg_id in &duplicates[1..] {
@@ -2,8 +2,14 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use super::fixtures::{self, Fixture};
-use crate::graph::{DependencyLink, DotWrite, PackageDotVisitor, PackageMetadata};
+use crate::diff::{ChangeKind, DiffEntry, DiffOptions};
+use crate::graph::{
+    DependencyDirection, DependencyLink, DotWrite, PackageDotVisitor, PackageGraph,
+    PackageMetadata, PackageSource, ParsedPackageId, SortKey,
+};
+use crate::Error;
 use cargo_metadata::PackageId;
+use std::collections::{BTreeSet, HashSet};
 use std::fmt;
 use std::iter;
 
@@ -81,6 +87,280 @@ fn metadata1() {
     );
 }
 
+#[test]
+fn membership_sets() {
+    let mut metadata1 = Fixture::metadata1();
+    let testcrate = fixtures::package_id(fixtures::METADATA1_TESTCRATE);
+    let datatest = fixtures::package_id(fixtures::METADATA1_DATATEST);
+    let region = fixtures::package_id(fixtures::METADATA1_REGION);
+
+    let graph = metadata1.graph();
+    assert!(
+        graph.in_workspace(&testcrate),
+        "testcrate is in the workspace"
+    );
+    assert!(
+        !graph.in_workspace(&datatest),
+        "datatest is not in the workspace"
+    );
+    assert!(
+        !graph.is_direct_third_party(&testcrate),
+        "workspace members aren't third-party"
+    );
+    assert!(
+        graph.is_direct_third_party(&datatest),
+        "datatest is a direct third-party dependency"
+    );
+    assert!(
+        !graph.is_direct_third_party(&region),
+        "region is only a transitive dependency"
+    );
+
+    // Removing the edge from the workspace should update the direct third-party set.
+    let graph = metadata1.graph_mut();
+    graph.retain_edges(|_, link| !link.from.in_workspace());
+    graph.verify().expect("graph verification should succeed");
+    assert!(
+        !graph.is_direct_third_party(&datatest),
+        "datatest is no longer a direct dependency"
+    );
+}
+
+#[test]
+fn sorted_ids() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+    let select = graph
+        .select_transitive_deps(iter::once(&fixtures::package_id(
+            fixtures::METADATA1_REGION,
+        )))
+        .unwrap();
+
+    let names: Vec<_> = select
+        .clone()
+        .into_ids_sorted_by(SortKey::Name)
+        .into_iter()
+        .map(|id| graph.metadata(id).unwrap().name())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "bitflags",
+            "libc",
+            "mach",
+            "region",
+            "winapi",
+            "winapi-i686-pc-windows-gnu",
+            "winapi-x86_64-pc-windows-gnu",
+        ],
+        "sorted by name"
+    );
+
+    let versions: Vec<_> = select
+        .clone()
+        .into_ids_sorted_by(SortKey::Version)
+        .into_iter()
+        .map(|id| graph.metadata(id).unwrap().version().clone())
+        .collect();
+    let mut expected_versions = versions.clone();
+    expected_versions.sort();
+    assert_eq!(versions, expected_versions, "sorted by version");
+
+    let topo: Vec<_> = select.clone().into_ids_sorted_by(SortKey::Topo);
+    let expected_topo: Vec<_> = select.into_iter_ids(None).collect();
+    assert_eq!(topo, expected_topo, "topo order matches into_iter_ids");
+}
+
+#[test]
+fn depths() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+    let testcrate = fixtures::package_id(fixtures::METADATA1_TESTCRATE);
+    let datatest = fixtures::package_id(fixtures::METADATA1_DATATEST);
+    // The longest chain in metadata1 is:
+    // testcrate -> datatest -> datatest-derive -> syn -> quote -> proc-macro2 -> unicode-xid
+    let unicode_xid = fixtures::package_id(
+        "unicode-xid 0.2.0 (registry+https://github.com/rust-lang/crates.io-index)",
+    );
+
+    let depths = graph.depths(DependencyDirection::Forward);
+    assert_eq!(
+        depths.len(),
+        graph.package_count(),
+        "all packages have depths"
+    );
+    assert_eq!(depths[&testcrate], 0, "testcrate is a root");
+    assert_eq!(depths[&datatest], 1, "datatest is a direct dependency");
+    assert_eq!(
+        depths[&unicode_xid], 6,
+        "unicode-xid is at the end of the chain"
+    );
+    assert_eq!(depths.values().max(), Some(&6), "longest chain has 6 links");
+
+    let reverse_depths = graph.depths(DependencyDirection::Reverse);
+    assert_eq!(reverse_depths[&unicode_xid], 0, "unicode-xid has no deps");
+    assert_eq!(
+        reverse_depths[&testcrate], 6,
+        "testcrate is at the end of the chain"
+    );
+}
+
+#[test]
+fn longest_chain() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+    let testcrate = fixtures::package_id(fixtures::METADATA1_TESTCRATE);
+
+    // There are several chains with 6 links in metadata1, e.g.
+    // testcrate -> datatest -> datatest-derive -> syn -> quote -> proc-macro2 -> unicode-xid
+    for direction in &[DependencyDirection::Forward, DependencyDirection::Reverse] {
+        let chain = graph.longest_chain(*direction);
+        assert_eq!(chain.len(), 7, "{:?}: longest chain has 6 links", direction);
+        for pair in chain.windows(2) {
+            assert!(
+                graph
+                    .dep_links_directed(pair[0], *direction)
+                    .expect("valid package ID")
+                    .any(|link| link.from.id() == pair[1] || link.to.id() == pair[1]),
+                "{:?}: {} is linked to {}",
+                direction,
+                pair[0],
+                pair[1]
+            );
+        }
+
+        let testcrate_idx = match direction {
+            DependencyDirection::Forward => 0,
+            DependencyDirection::Reverse => 6,
+        };
+        assert_eq!(
+            chain[testcrate_idx], &testcrate,
+            "{:?}: chain goes through testcrate",
+            direction
+        );
+    }
+}
+
+#[test]
+fn roots_and_leaves() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+    let testcrate = fixtures::package_id(fixtures::METADATA1_TESTCRATE);
+    let datatest = fixtures::package_id(fixtures::METADATA1_DATATEST);
+
+    let roots: Vec<_> = graph.root_ids(DependencyDirection::Forward, true).collect();
+    assert!(roots.contains(&&testcrate), "testcrate is a root");
+    assert!(!roots.contains(&&datatest), "datatest is not a root");
+    for root in &roots {
+        assert_eq!(
+            graph.reverse_dep_links(root).unwrap().count(),
+            0,
+            "{}: roots have no reverse dependencies",
+            root
+        );
+    }
+
+    let leaves: Vec<_> = graph.leaf_ids(DependencyDirection::Forward, true).collect();
+    assert!(!leaves.contains(&&testcrate), "testcrate is not a leaf");
+    for leaf in &leaves {
+        assert_eq!(
+            graph.dep_links(leaf).unwrap().count(),
+            0,
+            "{}: leaves have no dependencies",
+            leaf
+        );
+    }
+
+    let reverse_roots: BTreeSet<_> = graph.root_ids(DependencyDirection::Reverse, true).collect();
+    assert_eq!(
+        reverse_roots,
+        leaves.into_iter().collect(),
+        "reverse roots are forward leaves"
+    );
+
+    // Ignoring dev-only links can only produce more roots.
+    let metadata_libra = Fixture::metadata_libra();
+    let graph = metadata_libra.graph();
+    let with_dev: BTreeSet<_> = graph.root_ids(DependencyDirection::Forward, true).collect();
+    let without_dev: BTreeSet<_> = graph
+        .root_ids(DependencyDirection::Forward, false)
+        .collect();
+    assert!(
+        with_dev.len() < without_dev.len() && with_dev.is_subset(&without_dev),
+        "ignoring dev-only links produces more roots"
+    );
+}
+
+#[test]
+fn package_sources() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let source = |id: &str| graph.metadata(&fixtures::package_id(id)).unwrap().source();
+    assert_eq!(
+        source(fixtures::METADATA1_TESTCRATE),
+        &PackageSource::Path("/fakepath/testcrate".into()),
+        "testcrate is a path dependency"
+    );
+    assert_eq!(
+        source(fixtures::METADATA1_DATATEST),
+        &PackageSource::CratesIo,
+        "datatest is from crates.io"
+    );
+    assert_eq!(
+        source("walkdir 2.2.9 (git+https://github.com/BurntSushi/walkdir?tag=2.2.9#7c7013259eb9db400b3e5c7bc60330ca08068826)"),
+        &PackageSource::Git {
+            url: "https://github.com/BurntSushi/walkdir?tag=2.2.9".into(),
+            rev: "7c7013259eb9db400b3e5c7bc60330ca08068826".into(),
+        },
+        "walkdir was replaced with a git dependency"
+    );
+}
+
+#[test]
+fn package_sources_sparse_and_unknown() {
+    let mut metadata: serde_json::Value =
+        serde_json::from_str(fixtures::METADATA1).expect("metadata1 is valid JSON");
+    let sources = [
+        (
+            fixtures::METADATA1_DATATEST,
+            "sparse+https://index.crates.io/",
+        ),
+        (
+            fixtures::METADATA1_REGION,
+            "sparse+https://registry.example.com/",
+        ),
+        (fixtures::METADATA1_DTOA, "unknown+https://example.com/dtoa"),
+    ];
+    for package in metadata["packages"]
+        .as_array_mut()
+        .expect("packages is an array")
+    {
+        if let Some((_, source)) = sources.iter().find(|(id, _)| package["id"] == *id) {
+            package["source"] = (*source).into();
+        }
+    }
+    let graph = PackageGraph::from_json(metadata.to_string())
+        .expect("unknown source kinds don't cause errors");
+
+    let source = |id: &str| graph.metadata(&fixtures::package_id(id)).unwrap().source();
+    assert_eq!(
+        source(fixtures::METADATA1_DATATEST),
+        &PackageSource::CratesIo,
+        "the sparse crates.io index is crates.io"
+    );
+    assert_eq!(
+        source(fixtures::METADATA1_REGION),
+        &PackageSource::Registry("https://registry.example.com/".into()),
+        "other sparse registries are registries"
+    );
+    assert_eq!(
+        source(fixtures::METADATA1_DTOA),
+        &PackageSource::Other("unknown+https://example.com/dtoa".into()),
+        "unknown source kinds are preserved"
+    );
+}
+
 #[test]
 fn metadata2() {
     let metadata2 = Fixture::metadata2();
@@ -93,6 +373,997 @@ fn metadata_libra() {
     metadata_libra.verify();
 }
 
+#[test]
+fn runtime_links() {
+    let mut metadata_libra = Fixture::metadata_libra();
+    let graph = metadata_libra.graph_mut();
+    assert!(
+        graph
+            .select_all()
+            .into_iter_links(None)
+            .any(|link| link.edge.build_only()),
+        "libra has build-only links"
+    );
+
+    graph.retain_edges(|_, link| !link.edge.build_only() && !link.edge.dev_only());
+    graph.verify().expect("graph verification should succeed");
+    for link in graph.select_all().into_iter_links(None) {
+        assert!(
+            link.edge.normal().is_some(),
+            "{} -> {}: runtime links should have normal dependencies",
+            link.from.id(),
+            link.to.id(),
+        );
+    }
+}
+
+#[test]
+fn diff_graphs() {
+    let metadata1 = Fixture::metadata1();
+    let metadata2 = Fixture::metadata2();
+
+    let diff = DiffOptions.diff_graphs(metadata1.graph(), metadata1.graph());
+    assert!(diff.is_empty(), "a graph diffed against itself is empty");
+
+    let diff = DiffOptions.diff_graphs(metadata1.graph(), metadata2.graph());
+    let names = |entries: &[DiffEntry]| -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| entry.package_id.name().to_string())
+            .collect()
+    };
+    assert_eq!(names(&diff.added), vec!["testworkspace-crate", "walkdir"]);
+    assert_eq!(
+        names(&diff.removed),
+        vec!["bitflags", "libc", "mach", "quote", "region", "testcrate"]
+    );
+    assert_eq!(diff.changed.len(), 1, "one package changed");
+    assert_eq!(diff.changed[0].old.name(), "walkdir");
+    assert_eq!(
+        diff.changed[0].new.source(),
+        None,
+        "walkdir is now a path dependency"
+    );
+    assert_eq!(
+        diff.changed[0].kind,
+        ChangeKind::Patched,
+        "same version, different source"
+    );
+}
+
+#[test]
+fn diff_graphs_git_revision() {
+    let metadata1 = Fixture::metadata1();
+    let old_rev = "7c7013259eb9db400b3e5c7bc60330ca08068826";
+    let new_rev = "0123456789abcdef0123456789abcdef01234567";
+    let new_graph = PackageGraph::from_json(fixtures::METADATA1.replace(old_rev, new_rev))
+        .expect("valid metadata");
+
+    let diff = DiffOptions.diff_graphs(metadata1.graph(), &new_graph);
+    assert!(diff.added.is_empty(), "no packages were added");
+    assert!(diff.removed.is_empty(), "no packages were removed");
+    assert_eq!(diff.changed.len(), 1, "one package changed");
+    let changed = &diff.changed[0];
+    assert_eq!(changed.old.name(), "walkdir");
+    assert_eq!(
+        changed.new.source(),
+        Some(
+            format!(
+                "git+https://github.com/BurntSushi/walkdir?tag=2.2.9#{}",
+                new_rev
+            )
+            .as_str()
+        ),
+        "the source includes the new revision"
+    );
+    assert_eq!(
+        changed.kind,
+        ChangeKind::SourceChanged,
+        "a new git revision is not an override"
+    );
+}
+
+#[test]
+fn graph_diff_links() {
+    let metadata_libra = Fixture::metadata_libra();
+    let graph = metadata_libra.graph();
+
+    assert!(
+        graph.diff(graph).is_empty(),
+        "a graph diffed against itself is empty"
+    );
+
+    let mut no_dev_graph = graph.clone();
+    no_dev_graph.retain_edges(|_, link| !link.edge.dev_only());
+    let dev_only_count = graph
+        .select_all()
+        .into_iter_links(None)
+        .filter(|link| link.edge.dev_only())
+        .count();
+    assert!(dev_only_count > 0, "libra has dev-only links");
+
+    let diff = graph.diff(&no_dev_graph);
+    assert!(diff.packages.is_empty(), "no packages were removed");
+    assert!(diff.added_links.is_empty(), "no links were added");
+    assert_eq!(diff.removed_links.len(), dev_only_count);
+
+    let diff = no_dev_graph.diff(graph);
+    assert_eq!(diff.added_links.len(), dev_only_count);
+    assert!(diff.removed_links.is_empty(), "no links were removed");
+    assert!(diff.changed_links.is_empty(), "no links were changed");
+}
+
+#[test]
+fn graph_diff_changed_links() {
+    let metadata1 = Fixture::metadata1();
+
+    // Turn datatest's normal dependency on region into a dev-dependency.
+    let mut metadata: serde_json::Value =
+        serde_json::from_str(fixtures::METADATA1).expect("metadata1 is valid JSON");
+    let datatest = metadata["packages"]
+        .as_array_mut()
+        .expect("packages is an array")
+        .iter_mut()
+        .find(|package| package["id"] == fixtures::METADATA1_DATATEST)
+        .expect("datatest is in metadata1");
+    for dep in datatest["dependencies"]
+        .as_array_mut()
+        .expect("dependencies is an array")
+    {
+        if dep["name"] == "region" {
+            dep["kind"] = "dev".into();
+        }
+    }
+    let new_graph = PackageGraph::from_json(metadata.to_string()).expect("valid metadata");
+
+    let diff = metadata1.graph().diff(&new_graph);
+    assert!(diff.packages.is_empty(), "no packages were changed");
+    assert!(diff.added_links.is_empty(), "no links were added");
+    assert!(diff.removed_links.is_empty(), "no links were removed");
+    assert_eq!(diff.changed_links.len(), 1, "one link was changed");
+    let changed = &diff.changed_links[0];
+    assert_eq!(
+        (changed.from.name(), changed.to.name()),
+        ("datatest", "region")
+    );
+    assert_eq!(changed.old_kinds, vec!["normal"]);
+    assert_eq!(changed.new_kinds, vec!["dev"]);
+    assert!(changed.new.dev_only(), "the new link is dev-only");
+    assert!(!diff.is_empty());
+}
+
+#[test]
+fn minimal_version_violations() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let violations = graph.minimal_version_violations();
+    for (from, to, req, min_version) in &violations {
+        let to_version = graph.metadata(to).expect("valid package ID").version();
+        assert!(
+            req.matches(min_version),
+            "{} -> {}: min version {} matches {}",
+            from,
+            to,
+            min_version,
+            req
+        );
+        assert!(
+            to_version > min_version,
+            "{} -> {}: resolved version {} is higher than {}",
+            from,
+            to,
+            to_version,
+            min_version
+        );
+    }
+
+    let find_min_version = |from: &str, to: &str| {
+        violations
+            .iter()
+            .find(|(from_id, to_id, _, _)| {
+                from_id == &fixtures::package_id(from) && to_id == &fixtures::package_id(to)
+            })
+            .map(|(_, _, _, min_version)| min_version.to_string())
+    };
+    assert_eq!(
+        find_min_version(fixtures::METADATA1_TESTCRATE, fixtures::METADATA1_DATATEST),
+        Some("0.4.1".to_string()),
+        "testcrate depends on datatest ^0.4.1 and resolves to 0.4.2"
+    );
+    assert_eq!(
+        find_min_version(
+            "yaml-rust 0.4.3 (registry+https://github.com/rust-lang/crates.io-index)",
+            "linked-hash-map 0.5.2 (registry+https://github.com/rust-lang/crates.io-index)"
+        ),
+        Some("0.0.9".to_string()),
+        "multiple predicates: >= 0.0.9, < 0.6"
+    );
+}
+
+#[test]
+fn dominators() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+    let testcrate_id = fixtures::package_id(fixtures::METADATA1_TESTCRATE);
+    let datatest_id = fixtures::package_id(fixtures::METADATA1_DATATEST);
+    let region_id = fixtures::package_id(fixtures::METADATA1_REGION);
+
+    let dominators = graph.dominators(DependencyDirection::Forward);
+    assert_eq!(
+        dominators
+            .immediate_dominator(&testcrate_id)
+            .expect("valid package ID"),
+        None,
+        "testcrate is a root"
+    );
+    assert_eq!(
+        dominators
+            .immediate_dominator(&datatest_id)
+            .expect("valid package ID"),
+        Some(&testcrate_id),
+        "testcrate is the only package that depends on datatest"
+    );
+
+    let dominated_names: BTreeSet<_> = dominators
+        .dominated_by(&region_id)
+        .expect("valid package ID")
+        .into_iter()
+        .map(|id| graph.metadata(id).expect("valid package ID").name())
+        .collect();
+    assert_eq!(
+        dominated_names,
+        vec!["bitflags", "libc", "mach"].into_iter().collect(),
+        "packages only pulled in by region"
+    );
+
+    let unknown_id = fixtures::package_id("fake-package 0.1.0 (path+file:///fakepath/fake)");
+    assert!(dominators.dominated_by(&unknown_id).is_err());
+}
+
+#[test]
+fn declared_dependencies() {
+    let metadata2 = Fixture::metadata2();
+    let graph = metadata2.graph();
+
+    for package in graph.packages() {
+        // Every resolved link should correspond to a declared dependency.
+        for link in graph.dep_links(package.id()).expect("valid package ID") {
+            assert!(
+                package
+                    .declared_dependencies()
+                    .iter()
+                    .any(|dep| dep.name == link.to.name()),
+                "{} -> {}: resolved link should be declared",
+                package.id(),
+                link.to.id(),
+            );
+        }
+    }
+}
+
+#[test]
+fn scc_groups() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    // metadata1 doesn't have any cycles, so every package is in a group by itself.
+    let scc_groups = graph.scc_groups();
+    assert_eq!(scc_groups.groups.len(), graph.package_count());
+    assert_eq!(scc_groups.links.len(), graph.link_count());
+    assert_eq!(scc_groups.cycles().count(), 0, "no cycles");
+    for (from, to) in &scc_groups.links {
+        assert!(from < to, "groups are in topological order");
+    }
+}
+
+#[test]
+fn acyclic_non_dev() {
+    for fixture in &[
+        Fixture::metadata1(),
+        Fixture::metadata2(),
+        Fixture::metadata_libra(),
+    ] {
+        assert_eq!(fixture.graph().check_acyclic_non_dev(), Ok(()));
+    }
+}
+
+#[test]
+fn workspace_dev_only_direct() {
+    let metadata1 = Fixture::metadata1();
+    assert!(
+        metadata1.graph().workspace_dev_only_direct().is_empty(),
+        "testcrate's only dependency is also a normal dependency"
+    );
+
+    let metadata_libra = Fixture::metadata_libra();
+    let graph = metadata_libra.graph();
+    let dev_only = graph.workspace_dev_only_direct();
+    assert!(!dev_only.is_empty(), "libra has dev-only dependencies");
+
+    let mut no_dev_graph = graph.clone();
+    no_dev_graph.retain_edges(|_, link| !link.edge.dev_only());
+    let non_dev_reachable: BTreeSet<_> = no_dev_graph
+        .select_transitive_deps(graph.workspace().member_ids())
+        .expect("valid package IDs")
+        .into_iter_ids(None)
+        .collect();
+
+    for package_id in dev_only {
+        assert!(
+            !graph.in_workspace(package_id),
+            "{} is third-party",
+            package_id
+        );
+        assert!(
+            !non_dev_reachable.contains(package_id),
+            "{} is not reachable through non-dev links",
+            package_id
+        );
+        assert!(
+            graph
+                .reverse_dep_links(package_id)
+                .expect("valid package ID")
+                .any(|link| link.from.in_workspace() && link.edge.dev_only()),
+            "{} is a direct dev-dependency of a workspace member",
+            package_id
+        );
+    }
+}
+
+#[test]
+fn package_metadata_ord() {
+    let metadata2 = Fixture::metadata2();
+    let graph = metadata2.graph();
+
+    let mut packages: Vec<_> = graph.packages().collect();
+    packages.sort();
+    for pair in packages.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        assert!(
+            (a.name(), a.version()) <= (b.name(), b.version()),
+            "{} sorted before {}",
+            a.id(),
+            b.id()
+        );
+        assert_ne!(a, b, "package IDs are unique");
+    }
+}
+
+#[test]
+fn optional_edges() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let link_between = |from: &str, to: &str| {
+        let to_id = fixtures::package_id(to);
+        graph
+            .dep_links(&fixtures::package_id(from))
+            .expect("valid package ID")
+            .find(|link| link.to.id() == &to_id)
+            .expect("link should exist")
+    };
+
+    // testcrate lists datatest as an optional build dependency, but a mandatory normal and dev
+    // dependency.
+    let link = link_between(fixtures::METADATA1_TESTCRATE, fixtures::METADATA1_DATATEST);
+    assert!(
+        link.edge.build().expect("build dependency").optional(),
+        "optional in the build section"
+    );
+    assert!(
+        !link.edge.is_optional(),
+        "mandatory in another section, so not optional"
+    );
+
+    let link = link_between(fixtures::METADATA1_DATATEST, fixtures::METADATA1_REGION);
+    assert!(link.edge.is_optional(), "datatest -> region is optional");
+}
+
+#[test]
+fn packages_by_edition() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let testcrate = graph
+        .metadata(&fixtures::package_id(fixtures::METADATA1_TESTCRATE))
+        .expect("valid package ID");
+    assert_eq!(testcrate.edition(), "2018", "testcrate uses edition 2018");
+    let region = graph
+        .metadata(&fixtures::package_id(fixtures::METADATA1_REGION))
+        .expect("valid package ID");
+    assert_eq!(region.edition(), "2015", "region uses edition 2015");
+
+    assert_eq!(graph.packages_by_edition("2015").count(), 24);
+    assert_eq!(graph.packages_by_edition("2018").count(), 8);
+    assert_eq!(graph.packages_by_edition("2021").count(), 0);
+}
+
+#[test]
+fn renamed_package_name() {
+    let metadata2 = Fixture::metadata2();
+    let graph = metadata2.graph();
+
+    let mut names: Vec<_> = graph
+        .dep_links(&fixtures::package_id(fixtures::METADATA2_TESTCRATE))
+        .expect("valid package ID")
+        .filter(|link| link.to.name() == "walkdir")
+        .map(|link| (link.edge.dep_name(), link.edge.package_name()))
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            ("walkdir", "walkdir"),
+            ("walkdir-crates-io", "walkdir"),
+            ("walkdir-nuevo", "walkdir"),
+        ],
+        "renamed dependencies keep their registry names"
+    );
+}
+
+#[test]
+fn feature_counts() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let datatest = graph
+        .metadata(&fixtures::package_id(fixtures::METADATA1_DATATEST))
+        .expect("valid package ID");
+    let mut features: Vec<_> = datatest.named_features().collect();
+    features.sort();
+    assert_eq!(features, vec!["default", "unsafe_test_runner"]);
+    assert_eq!(datatest.named_feature_count(), 2);
+    assert_eq!(
+        datatest.optional_dependency_count(),
+        1,
+        "region is an optional dependency"
+    );
+
+    let region = graph
+        .metadata(&fixtures::package_id(fixtures::METADATA1_REGION))
+        .expect("valid package ID");
+    assert_eq!(region.named_feature_count(), 0);
+    assert_eq!(region.optional_dependency_count(), 0);
+}
+
+#[test]
+fn parse_package_ids() {
+    let metadata1 = Fixture::metadata1();
+    for package in metadata1.graph().packages() {
+        let parsed = ParsedPackageId::parse(&package.id().repr).expect("valid package ID");
+        assert_eq!(parsed.package_id(), package.id());
+        assert_eq!(parsed.name(), package.name());
+        assert_eq!(parsed.version(), package.version());
+    }
+
+    let parsed = ParsedPackageId::parse(fixtures::METADATA1_TESTCRATE).expect("valid package ID");
+    assert_eq!(parsed.source(), "path+file:///fakepath/testcrate");
+
+    for invalid in &[
+        "",
+        "testcrate",
+        "testcrate 0.1.0",
+        "testcrate 0.1.0 (path+file:///fakepath/testcrate",
+        "testcrate 0.1 (path+file:///fakepath/testcrate)",
+        "test crate 0.1.0 (path+file:///fakepath/testcrate)",
+        "testcrate 0.1.0 (/fakepath/testcrate)",
+    ] {
+        assert!(
+            ParsedPackageId::parse(invalid).is_err(),
+            "'{}' should fail to parse",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn workspace_dependents() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let dependents: Vec<_> = graph
+        .workspace_dependents(&fixtures::package_id(fixtures::METADATA1_DATATEST))
+        .expect("valid package ID")
+        .map(|package| package.id())
+        .collect();
+    assert_eq!(
+        dependents,
+        vec![&fixtures::package_id(fixtures::METADATA1_TESTCRATE)]
+    );
+    assert_eq!(
+        graph
+            .workspace_dependents(&fixtures::package_id(fixtures::METADATA1_REGION))
+            .expect("valid package ID")
+            .count(),
+        0,
+        "region is only a transitive dependency"
+    );
+
+    let metadata_libra = Fixture::metadata_libra();
+    let graph = metadata_libra.graph();
+    for package_id in graph.package_ids() {
+        if graph.in_workspace(package_id) {
+            continue;
+        }
+        let has_dependents = graph
+            .workspace_dependents(package_id)
+            .expect("valid package ID")
+            .next()
+            .is_some();
+        assert_eq!(
+            has_dependents,
+            graph.is_direct_third_party(package_id),
+            "{}: workspace dependents match direct third-party set",
+            package_id
+        );
+    }
+}
+
+#[test]
+fn resolve_spec() {
+    let metadata2 = Fixture::metadata2();
+    let graph = metadata2.graph();
+
+    let walkdir_registry = fixtures::package_id(
+        "walkdir 2.2.9 (registry+https://github.com/rust-lang/crates.io-index)",
+    );
+    let walkdir_nuevo =
+        fixtures::package_id("walkdir 0.1.0 (path+file:///Users/fakeuser/local/walkdir)");
+    let walkdir_workspace = fixtures::package_id(fixtures::METADATA2_WALKDIR);
+
+    for (spec, expected) in &[
+        ("walkdir:0.1.0", &walkdir_nuevo),
+        (
+            "https://github.com/rust-lang/crates.io-index#walkdir:2.2.9",
+            &walkdir_registry,
+        ),
+        (
+            "https://github.com/rust-lang/crates.io-index#walkdir",
+            &walkdir_registry,
+        ),
+        (
+            "file:///Users/fakeuser/local/testworkspace/walkdir",
+            &walkdir_workspace,
+        ),
+        (
+            "file:///Users/fakeuser/local/testworkspace/walkdir#2.2.9",
+            &walkdir_workspace,
+        ),
+    ] {
+        assert_eq!(
+            graph.resolve_spec(spec).expect("spec should resolve"),
+            *expected,
+            "spec '{}' resolves correctly",
+            spec
+        );
+    }
+
+    for spec in &[
+        "walkdir",
+        "walkdir:2.2.9",
+        "walkdir:2.2",
+        "nonexistent",
+        "file:///Users/fakeuser/local/testworkspace/walkdir#0.1.0",
+    ] {
+        assert!(
+            graph.resolve_spec(spec).is_err(),
+            "spec '{}' should fail to resolve",
+            spec
+        );
+    }
+}
+
+#[test]
+fn resolve_spec_new_id_format() {
+    // Newer versions of Cargo use package IDs of the form "kind+url#name@version".
+    let old_walkdir_registry =
+        "walkdir 2.2.9 (registry+https://github.com/rust-lang/crates.io-index)";
+    let new_walkdir_registry =
+        "registry+https://github.com/rust-lang/crates.io-index#walkdir@2.2.9";
+    let new_walkdir_workspace = "path+file:///Users/fakeuser/local/testworkspace/walkdir#2.2.9";
+    let json = fixtures::METADATA2
+        .replace(old_walkdir_registry, new_walkdir_registry)
+        .replace(fixtures::METADATA2_WALKDIR, new_walkdir_workspace);
+    let graph = PackageGraph::from_json(json).expect("valid metadata");
+
+    let walkdir_registry = fixtures::package_id(new_walkdir_registry);
+    let walkdir_workspace = fixtures::package_id(new_walkdir_workspace);
+    for (spec, expected) in &[
+        (
+            "https://github.com/rust-lang/crates.io-index#walkdir:2.2.9",
+            &walkdir_registry,
+        ),
+        (new_walkdir_registry, &walkdir_registry),
+        (
+            "file:///Users/fakeuser/local/testworkspace/walkdir",
+            &walkdir_workspace,
+        ),
+        (new_walkdir_workspace, &walkdir_workspace),
+    ] {
+        assert_eq!(
+            graph.resolve_spec(spec).expect("spec should resolve"),
+            *expected,
+            "spec '{}' resolves correctly",
+            spec
+        );
+    }
+}
+
+#[test]
+fn host_target_split() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let split = graph
+        .host_target_split(graph.workspace().member_ids())
+        .expect("valid package IDs");
+    let version_check = fixtures::package_id(
+        "version_check 0.9.1 (registry+https://github.com/rust-lang/crates.io-index)",
+    );
+    assert_eq!(
+        split.host,
+        vec![&version_check],
+        "version_check is only a build dependency"
+    );
+    assert!(split
+        .target
+        .contains(&&fixtures::package_id(fixtures::METADATA1_TESTCRATE)));
+    assert!(split
+        .target
+        .contains(&&fixtures::package_id(fixtures::METADATA1_DATATEST)));
+
+    let metadata_libra = Fixture::metadata_libra();
+    let graph = metadata_libra.graph();
+    let split = graph
+        .host_target_split(graph.workspace().member_ids())
+        .expect("valid package IDs");
+    let target: HashSet<_> = split.target.iter().collect();
+    assert!(
+        split
+            .host
+            .iter()
+            .all(|package_id| !target.contains(package_id)),
+        "host and target packages are disjoint"
+    );
+}
+
+#[test]
+fn reachability_matrix() {
+    for fixture in &[Fixture::metadata1(), Fixture::metadata2()] {
+        let graph = fixture.graph();
+        let matrix = graph.reachability_matrix();
+        let mut cache = graph.new_depends_cache();
+        for package_a in graph.package_ids() {
+            for package_b in graph.package_ids() {
+                assert_eq!(
+                    matrix
+                        .depends_on(package_a, package_b)
+                        .expect("valid package IDs"),
+                    cache
+                        .depends_on(package_a, package_b)
+                        .expect("valid package IDs"),
+                    "{} -> {}: reachability matrix agrees with depends_on",
+                    package_a,
+                    package_b
+                );
+            }
+        }
+
+        let unknown = fixtures::package_id("unknown 0.1.0 (path+file:///fakepath/unknown)");
+        let known = graph.package_ids().next().expect("graph is non-empty");
+        assert!(matrix.depends_on(&unknown, known).is_err());
+        assert!(matrix.depends_on(known, &unknown).is_err());
+    }
+}
+
+#[test]
+fn build_scripts() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let mut names: Vec<_> = graph
+        .packages()
+        .filter(|package| package.has_build_script())
+        .map(|package| package.name())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "bitflags",
+            "datatest",
+            "libc",
+            "memchr",
+            "proc-macro2",
+            "serde",
+            "syn",
+            "winapi",
+            "winapi-i686-pc-windows-gnu",
+            "winapi-x86_64-pc-windows-gnu",
+        ]
+    );
+}
+
+#[test]
+fn publish() {
+    let metadata_libra = Fixture::metadata_libra();
+    let graph = metadata_libra.graph();
+
+    let forbidden: Vec<_> = graph
+        .packages()
+        .filter(|package| package.publish_forbidden())
+        .collect();
+    assert_eq!(forbidden.len(), 76, "packages with publish = false");
+    for package in forbidden {
+        assert_eq!(package.publish(), Some(&[][..]));
+        assert!(
+            !package.is_publishable_to("crates-io"),
+            "{}: forbidden packages can't be published",
+            package.id()
+        );
+    }
+
+    for package in graph
+        .packages()
+        .filter(|package| !package.publish_forbidden())
+    {
+        assert_eq!(package.publish(), None);
+        assert!(
+            package.is_publishable_to("crates-io"),
+            "{}: unrestricted packages can be published anywhere",
+            package.id()
+        );
+    }
+}
+
+#[test]
+fn direct_counts() {
+    let metadata_libra = Fixture::metadata_libra();
+    let graph = metadata_libra.graph();
+
+    for package_id in graph.package_ids() {
+        assert_eq!(
+            graph.direct_dep_count(package_id),
+            Some(
+                graph
+                    .dep_links(package_id)
+                    .expect("valid package ID")
+                    .count()
+            ),
+            "{}: direct dep count matches dep_links",
+            package_id
+        );
+        assert_eq!(
+            graph.direct_dependent_count(package_id),
+            Some(
+                graph
+                    .reverse_dep_links(package_id)
+                    .expect("valid package ID")
+                    .count()
+            ),
+            "{}: direct dependent count matches reverse_dep_links",
+            package_id
+        );
+    }
+
+    let unknown = fixtures::package_id("unknown 0.1.0 (path+file:///fakepath/unknown)");
+    assert_eq!(graph.direct_dep_count(&unknown), None);
+    assert_eq!(graph.direct_dependent_count(&unknown), None);
+}
+
+#[test]
+fn distinct_targets() {
+    let metadata1 = Fixture::metadata1();
+    let targets: Vec<_> = metadata1.graph().distinct_targets().collect();
+    assert_eq!(
+        targets,
+        vec![
+            "cfg(any(target_os = \"macos\", target_os = \"ios\"))",
+            "cfg(windows)",
+            "i686-pc-windows-gnu",
+            "x86_64-pc-windows-gnu",
+        ]
+    );
+}
+
+#[test]
+fn parse_error_location() {
+    let datatest_id = format!("\"id\":\"{}\",", fixtures::METADATA1_DATATEST);
+    assert!(fixtures::METADATA1.contains(&datatest_id));
+    // Add a stray comma right after datatest's ID.
+    let json = fixtures::METADATA1.replacen(&datatest_id, &format!("{},", datatest_id), 1);
+
+    match PackageGraph::from_json(json) {
+        Err(Error::MetadataParseError(err, package_id)) => {
+            assert_eq!(err.line(), 1, "metadata1 is on a single line");
+            assert_eq!(
+                package_id,
+                Some(fixtures::package_id(fixtures::METADATA1_DATATEST)),
+                "nearest package ID is found"
+            );
+        }
+        other => panic!(
+            "expected a metadata parse error, found {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+fn from_json_bytes() {
+    let graph =
+        PackageGraph::from_json_bytes(fixtures::METADATA1.as_bytes()).expect("metadata1 is valid");
+    assert_eq!(
+        graph.package_count(),
+        Fixture::metadata1().graph().package_count(),
+        "same graph as from_json"
+    );
+    graph.verify().expect("graph is valid");
+}
+
+#[test]
+fn dependency_requests() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let datatest = fixtures::package_id(fixtures::METADATA1_DATATEST);
+    let link = graph
+        .dep_links(&fixtures::package_id(fixtures::METADATA1_TESTCRATE))
+        .expect("valid package ID")
+        .find(|link| link.to.id() == &datatest)
+        .expect("testcrate depends on datatest");
+
+    let dev = link.edge.dev().expect("dev dependency").as_request();
+    assert!(!dev.optional);
+    assert!(dev.uses_default_features);
+    assert_eq!(dev.features, &["unsafe_test_runner".to_string()]);
+    assert_eq!(dev.target, None);
+    assert_eq!(
+        dev.to_string(),
+        r#"{ version = "^0.4.2", features = ["unsafe_test_runner"] }"#
+    );
+
+    let build = link.edge.build().expect("build dependency").as_request();
+    assert_eq!(
+        build.to_string(),
+        r#"{ version = "^0.4.1", optional = true }"#
+    );
+}
+
+#[test]
+fn select_root_ids() {
+    let metadata1 = Fixture::metadata1();
+    let graph = metadata1.graph();
+
+    let testcrate = fixtures::package_id(fixtures::METADATA1_TESTCRATE);
+    let select = graph
+        .select_transitive_deps(iter::once(&testcrate))
+        .expect("valid package ID");
+    let selected: BTreeSet<_> = select.clone().into_iter_ids(None).collect();
+
+    let roots: Vec<_> = select.root_ids(DependencyDirection::Forward).collect();
+    assert_eq!(
+        roots,
+        vec![&testcrate],
+        "testcrate is the only forward root"
+    );
+
+    let reverse_roots: BTreeSet<_> = select.root_ids(DependencyDirection::Reverse).collect();
+    assert!(!reverse_roots.is_empty(), "selection has leaves");
+    for package_id in &reverse_roots {
+        assert!(
+            graph
+                .dep_links(package_id)
+                .expect("valid package ID")
+                .all(|link| !selected.contains(link.to.id())),
+            "{} has no dependencies within the selection",
+            package_id
+        );
+    }
+    assert_eq!(
+        reverse_roots,
+        select
+            .into_root_ids(DependencyDirection::Reverse)
+            .into_iter()
+            .collect(),
+        "root_ids matches into_root_ids"
+    );
+}
+
+#[test]
+fn missing_optional_fields() {
+    let mut metadata: serde_json::Value =
+        serde_json::from_str(fixtures::METADATA1).expect("metadata1 is valid JSON");
+    let datatest = metadata["packages"]
+        .as_array_mut()
+        .expect("packages is an array")
+        .iter_mut()
+        .find(|package| package["id"] == fixtures::METADATA1_DATATEST)
+        .expect("datatest is in metadata1")
+        .as_object_mut()
+        .expect("package is an object");
+    for field in &["edition", "authors", "categories", "features", "targets"] {
+        datatest.remove(*field);
+    }
+    let region_dep = datatest["dependencies"]
+        .as_array_mut()
+        .expect("dependencies is an array")
+        .iter_mut()
+        .find(|dep| dep["name"] == "region")
+        .expect("datatest depends on region")
+        .as_object_mut()
+        .expect("dependency is an object");
+    region_dep.remove("kind");
+    region_dep.remove("features");
+
+    let graph =
+        PackageGraph::from_json(metadata.to_string()).expect("missing fields are defaulted");
+    graph.verify().expect("graph is valid");
+
+    let datatest_id = fixtures::package_id(fixtures::METADATA1_DATATEST);
+    let warnings: Vec<_> = graph
+        .build_warnings()
+        .iter()
+        .map(|warning| {
+            assert_eq!(warning.package_id(), &datatest_id);
+            (warning.field(), warning.default())
+        })
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            ("features", "{}"),
+            ("targets", "[]"),
+            ("edition", "\"2015\""),
+            ("authors", "[]"),
+            ("categories", "[]"),
+            ("dependencies.region.kind", "null"),
+            ("dependencies.region.features", "[]"),
+        ]
+    );
+
+    let datatest = graph.metadata(&datatest_id).expect("valid package ID");
+    assert_eq!(datatest.edition(), "2015", "edition defaults to 2015");
+    assert!(datatest.authors().is_empty(), "authors default to empty");
+    assert_eq!(datatest.named_feature_count(), 0);
+    assert!(!datatest.has_build_script());
+
+    assert!(
+        Fixture::metadata1().graph().build_warnings().is_empty(),
+        "no warnings for complete metadata"
+    );
+}
+
+#[test]
+fn missing_defaulted_fields() {
+    // Only fields that cargo_metadata defaults itself are missing, so the metadata parses as is.
+    let mut metadata: serde_json::Value =
+        serde_json::from_str(fixtures::METADATA1).expect("metadata1 is valid JSON");
+    let datatest = metadata["packages"]
+        .as_array_mut()
+        .expect("packages is an array")
+        .iter_mut()
+        .find(|package| package["id"] == fixtures::METADATA1_DATATEST)
+        .expect("datatest is in metadata1")
+        .as_object_mut()
+        .expect("package is an object");
+    datatest.remove("edition");
+    datatest.remove("keywords");
+
+    let graph = PackageGraph::from_json(metadata.to_string()).expect("metadata is valid");
+    let datatest_id = fixtures::package_id(fixtures::METADATA1_DATATEST);
+    let warnings: Vec<_> = graph
+        .build_warnings()
+        .iter()
+        .map(|warning| {
+            assert_eq!(warning.package_id(), &datatest_id);
+            (warning.field(), warning.default())
+        })
+        .collect();
+    assert_eq!(warnings, vec![("edition", "\"2015\""), ("keywords", "[]")]);
+}
+
 struct NameVisitor;
 
 impl PackageDotVisitor for NameVisitor {
@@ -6,3 +6,5 @@ mod dot_tests;
 mod fixtures;
 mod graph_tests;
 mod reversed_tests;
+#[cfg(feature = "yanked")]
+mod yanked_tests;
@@ -0,0 +1,80 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use super::fixtures;
+use crate::graph::{PackageGraph, PackageSource};
+use crate::yanked::{index_path, RegistryIndex};
+use crate::Error;
+use std::env;
+use std::fs;
+use std::process;
+
+#[test]
+fn yanked_packages() {
+    // Use the sparse index as the source for datatest, like current versions of Cargo do.
+    let mut metadata: serde_json::Value =
+        serde_json::from_str(fixtures::METADATA1).expect("metadata1 is valid JSON");
+    for package in metadata["packages"]
+        .as_array_mut()
+        .expect("packages is an array")
+    {
+        if package["id"] == fixtures::METADATA1_DATATEST {
+            package["source"] = "sparse+https://index.crates.io/".into();
+        }
+    }
+    let graph = PackageGraph::from_json(metadata.to_string()).expect("valid metadata");
+
+    // Build an index with every crates.io package except region, with only datatest yanked.
+    let root = env::temp_dir().join(format!("guppy-yanked-tests-{}", process::id()));
+    let datatest_id = fixtures::package_id(fixtures::METADATA1_DATATEST);
+    let region_id = fixtures::package_id(fixtures::METADATA1_REGION);
+    for package in graph.packages() {
+        if package.source() != &PackageSource::CratesIo || package.id() == &region_id {
+            continue;
+        }
+        let path = root.join(index_path(package.name()));
+        fs::create_dir_all(path.parent().expect("index paths have a parent"))
+            .expect("created index directory");
+        let entry = serde_json::json!({
+            "name": package.name(),
+            "vers": package.version().to_string(),
+            "yanked": package.id() == &datatest_id,
+        });
+        fs::write(path, format!("{}\n", entry)).expect("wrote index file");
+    }
+
+    let index = RegistryIndex::new(&root).expect("index exists");
+    let result = index.yanked_packages(&graph);
+    fs::remove_dir_all(&root).expect("removed index");
+
+    let yanked: Vec<_> = result.yanked.iter().map(|package| package.id()).collect();
+    assert_eq!(yanked, vec![&datatest_id], "datatest is yanked");
+    let not_found: Vec<_> = result
+        .not_found
+        .iter()
+        .map(|(package, err)| {
+            assert!(
+                matches!(err, Error::RegistryIndexError(_)),
+                "unexpected error: {}",
+                err
+            );
+            package.id()
+        })
+        .collect();
+    assert_eq!(
+        not_found,
+        vec![&region_id],
+        "region is reported as missing from the index"
+    );
+}
+
+#[test]
+fn missing_index() {
+    let root = env::temp_dir().join(format!("guppy-missing-index-{}", process::id()));
+    match RegistryIndex::new(&root) {
+        Err(Error::RegistryIndexError(msg)) => {
+            assert!(msg.contains("not found"), "unexpected message: {}", msg)
+        }
+        other => panic!("expected a registry index error, found {:?}", other),
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright (c) The cargo-guppy Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Detect dependencies whose resolved version has been yanked from crates.io.
+//!
+//! This reads the registry index that Cargo keeps locally (under `~/.cargo/registry/index`), so it
+//! doesn't need network access. It requires the `yanked` feature.
+
+use crate::graph::{PackageGraph, PackageMetadata, PackageSource};
+use crate::Error;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A local copy of a registry index.
+#[derive(Clone, Debug)]
+pub struct RegistryIndex {
+    root: PathBuf,
+}
+
+impl RegistryIndex {
+    /// Uses the registry index at the given directory.
+    ///
+    /// Both Cargo's cache layout (`.cache/se/rd/serde`) and a checkout of the index
+    /// (`se/rd/serde`) are supported.
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, Error> {
+        let root = root.into();
+        if !root.is_dir() {
+            return Err(Error::RegistryIndexError(format!(
+                "registry index not found at {}",
+                root.display()
+            )));
+        }
+        Ok(Self { root })
+    }
+
+    /// Finds the local copy of the crates.io index in the Cargo home directory.
+    ///
+    /// The Cargo home directory is `$CARGO_HOME` if set, and `$HOME/.cargo` otherwise. Returns an
+    /// error if Cargo hasn't downloaded the crates.io index, e.g. if it has only been run offline.
+    pub fn crates_io() -> Result<Self, Error> {
+        let cargo_home = match env::var_os("CARGO_HOME") {
+            Some(cargo_home) => PathBuf::from(cargo_home),
+            None => match env::var_os("HOME") {
+                Some(home) => Path::new(&home).join(".cargo"),
+                None => {
+                    return Err(Error::RegistryIndexError(
+                        "could not determine the Cargo home directory".into(),
+                    ))
+                }
+            },
+        };
+
+        let index_dir = cargo_home.join("registry").join("index");
+        // The sparse protocol is preferred since it is the default in newer versions of Cargo.
+        for prefix in &["index.crates.io-", "github.com-"] {
+            if let Ok(entries) = fs::read_dir(&index_dir) {
+                for entry in entries.filter_map(Result::ok) {
+                    if entry.file_name().to_string_lossy().starts_with(prefix) {
+                        return Self::new(entry.path());
+                    }
+                }
+            }
+        }
+
+        Err(Error::RegistryIndexError(format!(
+            "crates.io index not found in {}",
+            index_dir.display()
+        )))
+    }
+
+    /// Returns the root directory of this index.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns true if this version of the crate has been yanked.
+    ///
+    /// Returns an error if the crate or version isn't present in the index.
+    pub fn is_yanked(&self, name: &str, version: &str) -> Result<bool, Error> {
+        let rel_path = index_path(name);
+        let contents = fs::read(self.root.join(".cache").join(&rel_path))
+            .or_else(|_| fs::read(self.root.join(&rel_path)))
+            .map_err(|err| {
+                Error::RegistryIndexError(format!(
+                    "crate '{}' not found in registry index at {}: {}",
+                    name,
+                    self.root.display(),
+                    err
+                ))
+            })?;
+
+        let yanked = parse_entries(&contents)
+            .find(|entry| entry.vers == version)
+            .map(|entry| entry.yanked);
+        yanked.ok_or_else(|| {
+            Error::RegistryIndexError(format!(
+                "version {} of crate '{}' not found in registry index at {}",
+                version,
+                name,
+                self.root.display()
+            ))
+        })
+    }
+
+    /// Looks up every package in this graph that is from crates.io, and returns the ones that have
+    /// been yanked.
+    ///
+    /// Packages that can't be found in the index, e.g. because it is out of date, don't stop the
+    /// scan. They are returned separately along with the error, so that they can be reported
+    /// rather than silently treated as not yanked.
+    pub fn yanked_packages<'g>(&self, graph: &'g PackageGraph) -> YankedPackages<'g> {
+        let mut yanked_packages = YankedPackages {
+            yanked: vec![],
+            not_found: vec![],
+        };
+        for package in graph.packages() {
+            if package.source() != &PackageSource::CratesIo {
+                continue;
+            }
+            match self.is_yanked(package.name(), &package.version().to_string()) {
+                Ok(true) => yanked_packages.yanked.push(package),
+                Ok(false) => {}
+                Err(err) => yanked_packages.not_found.push((package, err)),
+            }
+        }
+        yanked_packages.yanked.sort();
+        yanked_packages
+            .not_found
+            .sort_by_key(|(package, _)| *package);
+        yanked_packages
+    }
+}
+
+/// The results of looking up a graph's packages in a registry index, returned by
+/// `RegistryIndex::yanked_packages`.
+#[derive(Debug)]
+pub struct YankedPackages<'g> {
+    /// Packages that have been yanked, sorted by name and version.
+    pub yanked: Vec<&'g PackageMetadata>,
+    /// Packages that couldn't be looked up, along with the reason why, sorted by name and version.
+    pub not_found: Vec<(&'g PackageMetadata, Error)>,
+}
+
+#[derive(Deserialize)]
+struct IndexEntry {
+    vers: String,
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// Returns the path of a crate's file relative to the root of the index.
+pub(crate) fn index_path(name: &str) -> PathBuf {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => Path::new("1").join(&name),
+        2 => Path::new("2").join(&name),
+        3 => Path::new("3").join(&name[..1]).join(&name),
+        _ => Path::new(&name[..2]).join(&name[2..4]).join(&name),
+    }
+}
+
+/// Parses the entries in an index file.
+///
+/// Index checkouts have one JSON object per line, and Cargo's cache files have NUL-separated
+/// version strings and JSON objects after a short header. Both are handled by looking for JSON
+/// objects between separators.
+fn parse_entries(contents: &[u8]) -> impl Iterator<Item = IndexEntry> + '_ {
+    contents
+        .split(|b| *b == b'\0' || *b == b'\n')
+        .filter(|chunk| chunk.first() == Some(&b'{'))
+        .filter_map(|chunk| serde_json::from_slice(chunk).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_paths() {
+        assert_eq!(index_path("a"), Path::new("1/a"));
+        assert_eq!(index_path("cc"), Path::new("2/cc"));
+        assert_eq!(index_path("syn"), Path::new("3/s/syn"));
+        assert_eq!(index_path("Serde"), Path::new("se/rd/serde"));
+    }
+
+    #[test]
+    fn parse_index_entries() {
+        let checkout = b"{\"name\":\"foo\",\"vers\":\"0.1.0\",\"yanked\":false}\n\
+            {\"name\":\"foo\",\"vers\":\"0.1.1\",\"yanked\":true}\n";
+        let cache =
+            b"\x03\x02\0\0\0etag\x000.1.0\0{\"name\":\"foo\",\"vers\":\"0.1.0\",\"yanked\":false}\
+            \x000.1.1\0{\"name\":\"foo\",\"vers\":\"0.1.1\",\"yanked\":true}\0";
+
+        for contents in &[&checkout[..], &cache[..]] {
+            let entries: Vec<_> = parse_entries(contents)
+                .map(|entry| (entry.vers, entry.yanked))
+                .collect();
+            assert_eq!(
+                entries,
+                vec![("0.1.0".to_string(), false), ("0.1.1".to_string(), true)]
+            );
+        }
+    }
+}